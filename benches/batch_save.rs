@@ -0,0 +1,64 @@
+// Compares saving the rolling Sharpe and Sortino ratios of 500 symbols one transaction per
+// symbol against a single batch, on an in-memory database. Run with `cargo bench`.
+use market_int::store::{rolling_sharpe, sortino, sqlite};
+use std::time::{Duration, Instant};
+
+const SYMBOLS: usize = 500;
+const WINDOW: usize = 252;
+const RUNS: u32 = 5;
+
+fn main() {
+    let symbols: Vec<String> = (0..SYMBOLS).map(|i| format!("SYM{}", i)).collect();
+    let sharpes: Vec<(String, u32, f64)> = symbols
+        .iter()
+        .flat_map(|symbol| (0..WINDOW as u32).map(move |day| (symbol.clone(), day, 1.0)))
+        .collect();
+    let sortinos: Vec<(String, f64, u32)> = symbols
+        .iter()
+        .map(|symbol| (symbol.clone(), 1.0, 0))
+        .collect();
+
+    report(
+        "rolling Sharpe, per symbol",
+        time(|conn| {
+            for entries in sharpes.chunks(WINDOW) {
+                rolling_sharpe::save_rolling_sharpes_batch(conn, WINDOW, entries).unwrap();
+            }
+        }),
+    );
+    report(
+        "rolling Sharpe, batch",
+        time(|conn| rolling_sharpe::save_rolling_sharpes_batch(conn, WINDOW, &sharpes).unwrap()),
+    );
+    report(
+        "Sortino, per symbol",
+        time(|conn| {
+            for entry in &sortinos {
+                sortino::save_sortino_ratios_batch(conn, WINDOW, std::slice::from_ref(entry))
+                    .unwrap();
+            }
+        }),
+    );
+    report(
+        "Sortino, batch",
+        time(|conn| sortino::save_sortino_ratios_batch(conn, WINDOW, &sortinos).unwrap()),
+    );
+}
+
+// Average time of `save` over RUNS fresh databases.
+fn time(save: impl Fn(&mut rusqlite::Connection)) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let mut conn = sqlite::init_in_memory_connection().unwrap();
+        rolling_sharpe::create_table(&conn).unwrap();
+        sortino::create_table(&conn).unwrap();
+        let start = Instant::now();
+        save(&mut conn);
+        total += start.elapsed();
+    }
+    total / RUNS
+}
+
+fn report(name: &str, elapsed: Duration) {
+    println!("{:<28} {:>10.2?} ({} symbols)", name, elapsed, SYMBOLS);
+}
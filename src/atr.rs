@@ -1,88 +1,211 @@
 use crate::{
-    constants, model,
-    store::{self, candle, true_range},
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    plot,
+    store::{candle, true_range},
     symbols,
 };
 use rusqlite::Connection;
 
+/// How the true range of a candle is measured.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum AtrMode {
+    /// True range as a fraction of the price.
+    #[default]
+    Ratio,
+    /// True range in price units.
+    Absolute,
+}
+
+impl AtrMode {
+    /// Table the true ranges of the mode are saved to. Only ratio true ranges can be read as
+    /// the fractions option strike ranges are built from.
+    pub fn table(&self) -> &'static str {
+        match self {
+            AtrMode::Ratio => true_range::RATIO_TABLE,
+            AtrMode::Absolute => true_range::ABSOLUTE_TABLE,
+        }
+    }
+}
+
 pub fn calculate_and_save(
     symbols_file_path: &str, // Path to the file containing symbols.
-    conn: &mut Connection,   // Database connection.)
+    conn: &mut Connection,   // Database connection.
+    mode: AtrMode,
+    config: &Config,
+    plot: bool,                  // Log an ASCII chart of each symbol's EMA ATR history.
+    halt_on_discontinuity: bool, // Fail instead of warning when a price gap is found.
 ) -> model::Result<()> {
     let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
 
     // Initialize the candle table in the database.
-    store::true_range::create_table(conn)?;
+    true_range::create_table_named(conn, mode.table())?;
 
     let mut true_range_vec: Vec<model::TrueRange> = Vec::with_capacity(symbols.len() * 5);
+    let mut summary = model::RunSummary::default();
     // Iterate over each symbol.
     for symbol in symbols {
         // Fetch candle data for the current symbol from the database.
-        let candles = candle::get_candles(conn, symbol.as_str(), constants::CANDLE_COUNT)?;
-
-        // Aggregate 5 candles into one. Calculate the open, close, high, low based on each group of 5 candles
-        let weekly_candles: Vec<model::Candle> = candles
-            .chunks(5)
-            .map(|chunk| {
-                let open = chunk.first().map_or(0.0, |c| c.open); // Handle empty chunks
-                let close = chunk.last().map_or(0.0, |c| c.close); // Handle empty chunks
-                let high = chunk
-                    .iter()
-                    .map(|c| c.high)
-                    .max_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap();
-                let low = chunk
-                    .iter()
-                    .map(|c| c.low)
-                    .min_by(|a, b| a.partial_cmp(b).unwrap())
-                    .unwrap();
-                let volume: u32 = chunk.iter().map(|c| c.volume).sum();
-                model::Candle {
-                    symbol: symbol.clone(),
-                    open,
-                    high,
-                    low,
-                    close,
-                    volume,
-                    timestamp: chunk.first().map_or(0, |c| c.timestamp), // Handle empty chunks
-                }
-            })
-            .collect();
-
-        // candles here are now weekly candles
-        if weekly_candles.len() < 4 {
+        let candles = match load_candles(conn, &symbol, config) {
+            Ok(Some(candles)) => candles,
+            Ok(None) => {
+                summary.failure(&symbol, "not enough candles");
+                continue;
+            }
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping ATR calculation");
+                summary.failure(&symbol, err);
+                continue;
+            }
+        };
+
+        let gaps = candle::detect_price_discontinuity(&candles, constants::DISCONTINUITY_THRESHOLD);
+        for &i in &gaps {
             log::warn!(
-                "Not enough candles for {}, skipping ATR calculation",
-                symbol
+                symbol = symbol.as_str(),
+                index = i,
+                previous_close = candles[i - 1].close,
+                open = candles[i].open;
+                "Price discontinuity, possibly an unadjusted split"
             );
-            continue;
+        }
+        if halt_on_discontinuity && !gaps.is_empty() {
+            return Err(model::QuotesError::not_enough_candles(
+                &symbol,
+                format!("{} price discontinuities in the candles", gaps.len()),
+            ));
         }
 
-        // Calculate the ATR for the candles.
-        let trs = true_ranges_ratio(&weekly_candles);
-        let ema_atr = exponential_moving_average(&trs, 4)?;
-        let percentile_atr = percentile(&trs, constants::PERCENTILE)?;
-
-        true_range_vec.push(model::TrueRange {
-            symbol: symbol.clone(),
-            percentile_range: percentile_atr,
-            ema_range: ema_atr,
-            timestamp: weekly_candles.last().unwrap().timestamp,
-        });
+        match weekly_true_range(&symbol, &candles, mode, config) {
+            Ok(Some(tr)) => {
+                true_range_vec.push(tr);
+                summary.success(&symbol);
+            }
+            Ok(None) => summary.failure(&symbol, "not enough weekly candles"),
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping ATR calculation");
+                summary.failure(&symbol, err);
+            }
+        }
     }
 
     // Save the true ranges to the database.
-    true_range::save_true_ranges(conn, &true_range_vec)?;
-    Ok(())
+    true_range::save_true_ranges_to(conn, mode.table(), &true_range_vec)?;
+
+    if plot {
+        for tr in &true_range_vec {
+            let history = true_range::get_true_range_rolling(
+                conn,
+                mode.table(),
+                &tr.symbol,
+                constants::ATR_PLOT_WIDTH as u32,
+            )
+            .with_symbol(&tr.symbol)?;
+            let chart = plot::plot_atr_history_ascii(
+                &history,
+                constants::ATR_PLOT_WIDTH,
+                constants::ATR_PLOT_HEIGHT,
+            );
+            log::info!(symbol = tr.symbol.as_str(); "EMA ATR history:\n{}", chart);
+        }
+    }
+    summary.finish()
+}
+
+// Reads the candles of a symbol, or None when there are too few to calculate the ATR.
+fn load_candles(
+    conn: &Connection,
+    symbol: &str,
+    config: &Config,
+) -> model::Result<Option<Vec<model::Candle>>> {
+    // At least 16 daily candles are needed to form 4 weekly candles.
+    let summary = candle::get_candle_summary(conn, symbol)?;
+    if summary.count < 16 {
+        log::warn!(
+            symbol = symbol, count = summary.count;
+            "Not enough candles, skipping ATR calculation"
+        );
+        return Ok(None);
+    }
+    let candles = candle::get_candles_sorted_asc(conn, symbol, config.candle_count)?;
+    Ok(Some(candles))
+}
+
+// Aggregates the daily candles into weekly ones and reduces their true ranges to the EMA and
+// percentile ATR, or None when there are fewer than 4 weekly candles.
+fn weekly_true_range(
+    symbol: &str,
+    candles: &[model::Candle],
+    mode: AtrMode,
+    config: &Config,
+) -> model::Result<Option<model::TrueRange>> {
+    // Aggregate 5 candles into one.
+    let weekly_candles: Vec<model::Candle> = candles
+        .chunks(5)
+        .filter_map(model::Candle::from_weekly)
+        .collect();
+
+    // candles here are now weekly candles
+    if weekly_candles.len() < 4 {
+        log::warn!(symbol = symbol; "Not enough candles, skipping ATR calculation");
+        return Ok(None);
+    }
+
+    // Calculate the ATR for the candles.
+    let trs = true_ranges(&weekly_candles, mode);
+    let ema_atr = exponential_moving_average(&trs, 4)?;
+    let percentile_atr = percentile(&trs, config.percentile)?;
+
+    Ok(Some(model::TrueRange {
+        symbol: symbol.to_string(),
+        percentile_range: percentile_atr,
+        ema_range: ema_atr,
+        timestamp: weekly_candles.last().unwrap().timestamp,
+    }))
 }
 
-fn true_ranges_ratio(candles: &[model::Candle]) -> Vec<f64> {
+/// Fits a least-squares line through the EMA ATR values and returns its slope per record.
+/// A positive slope means volatility is rising. Returns 0.0 for fewer than two records.
+pub fn atr_trend(true_ranges: &[model::TrueRange]) -> f64 {
+    let n = true_ranges.len() as f64;
+    if true_ranges.len() < 2 {
+        return 0.0;
+    }
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = true_ranges.iter().map(|tr| tr.ema_range).sum::<f64>() / n;
+    let (covariance, variance) =
+        true_ranges
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(covariance, variance), (i, tr)| {
+                let dx = i as f64 - mean_x;
+                (
+                    covariance + dx * (tr.ema_range - mean_y),
+                    variance + dx * dx,
+                )
+            });
+    covariance / variance
+}
+
+fn true_ranges(candles: &[model::Candle], mode: AtrMode) -> Vec<f64> {
+    let true_range = match mode {
+        AtrMode::Ratio => true_range_ratio,
+        AtrMode::Absolute => true_range_absolute,
+    };
     candles
         .windows(2)
-        .map(|w| true_range_ratio(&w[1], &w[0]))
+        .map(|w| true_range(&w[1], &w[0]))
         .collect()
 }
 
+fn true_range_absolute(current: &model::Candle, previous: &model::Candle) -> f64 {
+    let range1 = current.high - current.low;
+    let range2 = (current.high - previous.close).abs();
+    let range3 = (current.low - previous.close).abs();
+    range1.max(range2).max(range3)
+}
+
 fn true_range_ratio(current: &model::Candle, previous: &model::Candle) -> f64 {
     let range1 = (current.high - current.low) / current.low;
     let range2 = calculate_range(current.high, previous.close);
@@ -98,29 +221,54 @@ fn ema(prev: f64, current: f64, multiplier: f64) -> f64 {
     current * multiplier + prev * (1.0 - multiplier)
 }
 
-fn exponential_moving_average(array: &[f64], period: u32) -> model::Result<f64> {
+/// Exponential moving average of the values, oldest first, with the smoothing factor
+/// `2 / (period + 1)`. The first value seeds the average, so the result is the EMA as of the
+/// last value. Fails when there are fewer values than `period`.
+///
+/// ```
+/// # use market_int::statistics::exponential_moving_average;
+/// let ema = exponential_moving_average(&[1.0, 2.0, 3.0], 3).unwrap();
+/// assert_eq!(ema, 2.25);
+/// assert!(exponential_moving_average(&[1.0, 2.0], 3).is_err());
+/// ```
+pub fn exponential_moving_average(array: &[f64], period: u32) -> model::Result<f64> {
     if array.len() < period as usize {
-        return Err(model::QuotesError::NotEnoughCandlesForStatistics(format!(
-            "Not enough candles for EMA calculation (period: {})",
-            period
-        )));
+        return Err(model::QuotesError::not_enough_candles(
+            "",
+            format!(
+                "Not enough candles for EMA calculation (period: {})",
+                period
+            ),
+        ));
     }
     let multiplier = 2.0 / (period as f64 + 1.0);
-    let mut ema_value = array[0]; // Initialize with the first value
-    for i in 1..array.len() {
-        ema_value = ema(ema_value, array[i], multiplier);
-    }
+    // Initialize with the first value.
+    let ema_value = array[1..]
+        .iter()
+        .fold(array[0], |prev, &current| ema(prev, current, multiplier));
     Ok(ema_value)
 }
 
-fn percentile(values: &[f64], percentile: f64) -> model::Result<f64> {
+/// Value at `percentile`, a fraction between 0 and 1, of the values, interpolating linearly
+/// between the two nearest ranks. Fails for no values or a percentile outside 0 to 1.
+///
+/// ```
+/// # use market_int::statistics::percentile;
+/// let values = [4.0, 1.0, 3.0, 2.0];
+/// assert_eq!(percentile(&values, 0.5).unwrap(), 2.5);
+/// assert!((percentile(&values, 0.9).unwrap() - 3.7).abs() < 1e-12);
+/// assert!(percentile(&values, 1.5).is_err());
+/// ```
+pub fn percentile(values: &[f64], percentile: f64) -> model::Result<f64> {
     if values.is_empty() {
-        return Err(model::QuotesError::NotEnoughCandlesForStatistics(
+        return Err(model::QuotesError::not_enough_candles(
+            "",
             "Not enough values for percentile calculation".to_string(),
         ));
     }
-    if percentile < 0.0 || percentile > 1.0 {
-        return Err(model::QuotesError::NotEnoughCandlesForStatistics(
+    if !(0.0..=1.0).contains(&percentile) {
+        return Err(model::QuotesError::not_enough_candles(
+            "",
             "Percentile must be between 0 and 1".to_string(),
         ));
     }
@@ -0,0 +1,106 @@
+use crate::{
+    model::{self, WithSymbol},
+    store::option_chain,
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Replays the stored snapshots of a symbol, selling at each snapshot the put whose DTE is
+/// closest to `dte` and, among those, whose delta is closest to `target_delta`. Each trade is
+/// closed at the last later snapshot still quoting the contract, for a P&L of
+/// `entry_mid - exit_mid` per share. Strikes without a stored delta are ignored.
+pub fn backtest_put_selling(
+    conn: &Connection,
+    symbol: &str,
+    target_delta: f64, // Absolute delta of the put to sell, e.g. 0.3.
+    dte: u32,          // Preferred days to expiration at entry.
+) -> model::Result<model::BacktestResult> {
+    let mut snapshots = Vec::new();
+    for (run_id, updated) in option_chain::list_snapshot_times(conn, symbol)? {
+        let chain = option_chain::get_option_chain_at(conn, symbol, &run_id, &updated)?;
+        snapshots.push(chain);
+    }
+
+    let mut result = model::BacktestResult {
+        win_count: 0,
+        loss_count: 0,
+        total_pnl: 0.0,
+        max_loss: 0.0,
+        max_gain: 0.0,
+    };
+    for (i, chain) in snapshots.iter().enumerate() {
+        let Some(entry) = select_put(chain, target_delta, dte) else {
+            continue;
+        };
+        let exit = snapshots[i + 1..]
+            .iter()
+            .rev()
+            .find_map(|later| later.iter().find(|c| same_contract(c, entry)));
+        let Some(exit) = exit else {
+            continue;
+        };
+
+        let pnl = entry.mid - exit.mid;
+        if pnl > 0.0 {
+            result.win_count += 1;
+        } else {
+            result.loss_count += 1;
+        }
+        result.total_pnl += pnl;
+        result.max_gain = result.max_gain.max(pnl);
+        result.max_loss = result.max_loss.min(pnl);
+    }
+    Ok(result)
+}
+
+/// Runs the put-selling backtest for every symbol and prints the results.
+pub fn print_backtests(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &Connection,       // Database connection.
+    target_delta: f64,
+    dte: u32,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    option_chain::create_table(conn)?;
+
+    println!(
+        "{:<8} {:>5} {:>6} {:>10} {:>10} {:>10}",
+        "SYMBOL", "WINS", "LOSSES", "TOTAL P&L", "MAX GAIN", "MAX LOSS"
+    );
+    for symbol in symbols {
+        let result = backtest_put_selling(conn, &symbol, target_delta, dte).with_symbol(&symbol)?;
+        println!(
+            "{:<8} {:>5} {:>6} {:>10.2} {:>10.2} {:>10.2}",
+            symbol,
+            result.win_count,
+            result.loss_count,
+            result.total_pnl,
+            result.max_gain,
+            result.max_loss
+        );
+    }
+    Ok(())
+}
+
+// Picks the put closest to the target DTE, breaking ties by distance to the target delta.
+fn select_put(
+    chain: &[model::OptionStrikeCandle],
+    target_delta: f64,
+    dte: u32,
+) -> Option<&model::OptionStrikeCandle> {
+    chain
+        .iter()
+        .filter(|c| c.side == model::OptionChainSide::Put && c.delta != 0.0)
+        .min_by(|a, b| {
+            a.dte.abs_diff(dte).cmp(&b.dte.abs_diff(dte)).then_with(|| {
+                let distance =
+                    |c: &model::OptionStrikeCandle| (c.delta.abs() - target_delta.abs()).abs();
+                distance(a).total_cmp(&distance(b))
+            })
+        })
+}
+
+fn same_contract(a: &model::OptionStrikeCandle, b: &model::OptionStrikeCandle) -> bool {
+    a.side == b.side && a.strike == b.strike && a.expiration == b.expiration
+}
@@ -0,0 +1,117 @@
+use crate::model;
+
+// Volatility bounds of the implied volatility search.
+const MIN_VOLATILITY: f64 = 0.0001;
+const MAX_VOLATILITY: f64 = 5.0;
+const MAX_ITERATIONS: usize = 100;
+
+/// Black-Scholes price of a European option. `years` is the time to expiration in years and
+/// `rate` the annual risk-free rate.
+pub fn price(
+    side: &model::OptionChainSide,
+    spot: f64,
+    strike: f64,
+    years: f64,
+    rate: f64,
+    volatility: f64,
+) -> f64 {
+    let discounted_strike = strike * (-rate * years).exp();
+    let d1 = ((spot / strike).ln() + (rate + volatility * volatility / 2.0) * years)
+        / (volatility * years.sqrt());
+    let d2 = d1 - volatility * years.sqrt();
+    match side {
+        model::OptionChainSide::Call => spot * norm_cdf(d1) - discounted_strike * norm_cdf(d2),
+        model::OptionChainSide::Put => discounted_strike * norm_cdf(-d2) - spot * norm_cdf(-d1),
+    }
+}
+
+/// Solves for the volatility at which the Black-Scholes price matches `option_price` by
+/// bisection. Returns None when the inputs are not positive or the price is outside what any
+/// volatility between `MIN_VOLATILITY` and `MAX_VOLATILITY` would give.
+pub fn implied_volatility(
+    side: &model::OptionChainSide,
+    option_price: f64,
+    spot: f64,
+    strike: f64,
+    years: f64,
+    rate: f64,
+) -> Option<f64> {
+    if option_price <= 0.0 || spot <= 0.0 || strike <= 0.0 || years <= 0.0 {
+        return None;
+    }
+    let (mut low, mut high) = (MIN_VOLATILITY, MAX_VOLATILITY);
+    if option_price < price(side, spot, strike, years, rate, low)
+        || option_price > price(side, spot, strike, years, rate, high)
+    {
+        return None;
+    }
+    // The price rises with volatility, so the bracket halves towards the match.
+    for _ in 0..MAX_ITERATIONS {
+        let mid = (low + high) / 2.0;
+        if price(side, spot, strike, years, rate, mid) < option_price {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+// Standard normal cumulative distribution, from the Abramowitz and Stegun 7.1.26 approximation
+// of the error function.
+fn norm_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * z);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-z * z).exp();
+    if x >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use model::OptionChainSide::{Call, Put};
+
+    #[test]
+    fn norm_cdf_is_a_half_at_zero_and_symmetric() {
+        assert!((norm_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((norm_cdf(1.0) + norm_cdf(-1.0) - 1.0).abs() < 1e-7);
+        assert!((norm_cdf(1.96) - 0.975).abs() < 1e-4);
+    }
+
+    #[test]
+    fn prices_satisfy_put_call_parity() {
+        let (spot, strike, years, rate, volatility) = (100.0, 95.0, 0.5, 0.04, 0.3);
+        let call = price(&Call, spot, strike, years, rate, volatility);
+        let put = price(&Put, spot, strike, years, rate, volatility);
+        let parity = spot - strike * (-rate * years).exp();
+        assert!((call - put - parity).abs() < 1e-6);
+    }
+
+    #[test]
+    fn implied_volatility_recovers_the_pricing_volatility() {
+        for side in [Put, Call] {
+            let option_price = price(&side, 100.0, 90.0, 0.25, 0.04, 0.35);
+            let iv = implied_volatility(&side, option_price, 100.0, 90.0, 0.25, 0.04).unwrap();
+            assert!((iv - 0.35).abs() < 1e-6, "{side}: {iv}");
+        }
+    }
+
+    #[test]
+    fn implied_volatility_is_none_when_no_volatility_matches_the_price() {
+        // A call is never worth more than the underlying, nor a put less than its discounted
+        // intrinsic value, so the bisection has no bracket to converge in.
+        assert_eq!(
+            implied_volatility(&Call, 150.0, 100.0, 90.0, 0.25, 0.04),
+            None
+        );
+        assert_eq!(implied_volatility(&Put, 0.5, 50.0, 90.0, 0.25, 0.04), None);
+        assert_eq!(implied_volatility(&Put, 1.0, 100.0, 90.0, 0.0, 0.04), None);
+    }
+}
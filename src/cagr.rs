@@ -0,0 +1,62 @@
+use crate::{
+    config::Config,
+    model::{self, WithSymbol},
+    store::{cagr, candle},
+    symbols,
+};
+use rusqlite::Connection;
+
+// Seconds in an average calendar year, leap years included.
+const SECONDS_PER_YEAR: f64 = 365.25 * 86_400.0;
+
+/// Calculates the compound annual growth rate of every symbol and saves it to the database.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    cagr::create_table(conn)?;
+
+    let mut cagr_vec = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let value = match calculate_cagr(&candles) {
+            Ok(value) => value,
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping CAGR");
+                continue;
+            }
+        };
+        cagr_vec.push((symbol, value, candles.last().unwrap().timestamp));
+    }
+
+    cagr::save_cagrs(conn, &cagr_vec)?;
+    Ok(())
+}
+
+/// Compound annual growth rate from the first to the last close, annualised over the time
+/// between the two candles. Candles must be sorted from oldest to newest.
+pub fn calculate_cagr(candles: &[model::Candle]) -> model::Result<f64> {
+    if candles.len() < 2 {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for CAGR calculation (candles: {})",
+                candles.len()
+            ),
+        ));
+    }
+    let first = &candles[0];
+    let last = &candles[candles.len() - 1];
+    let years = (last.timestamp as f64 - first.timestamp as f64) / SECONDS_PER_YEAR;
+    if years <= 0.0 || first.close <= 0.0 {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            "CAGR needs candles spanning some time and a positive first close".to_string(),
+        ));
+    }
+    Ok((last.close / first.close).powf(1.0 / years) - 1.0)
+}
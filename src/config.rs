@@ -0,0 +1,56 @@
+use std::{env, fmt::Display, str::FromStr};
+
+use crate::constants;
+
+/// Runtime settings. Each value can be overridden by an environment variable and
+/// otherwise falls back to its default in `constants.rs`.
+#[derive(Debug)]
+pub struct Config {
+    pub candle_count: u32,       // MARKET_INT_CANDLE_COUNT
+    pub percentile: f64,         // MARKET_INT_PERCENTILE
+    pub min_open_interest: u32,  // MARKET_INT_MIN_OPEN_INTEREST
+    pub risk_free_rate: f64,     // MARKET_INT_RISK_FREE_RATE
+    pub signal_rsi_sell: f64,    // MARKET_INT_SIGNAL_RSI_SELL
+    pub signal_rsi_warning: f64, // MARKET_INT_SIGNAL_RSI_WARNING
+    pub signal_min_sharpe: f64,  // MARKET_INT_SIGNAL_MIN_SHARPE
+    pub db_warn_size_mb: u64,    // MARKET_INT_DB_WARN_SIZE_MB
+}
+
+impl Config {
+    /// Reads the config from the environment.
+    pub fn from_env() -> Self {
+        Config {
+            candle_count: read_env("MARKET_INT_CANDLE_COUNT", constants::CANDLE_COUNT),
+            percentile: read_env("MARKET_INT_PERCENTILE", constants::PERCENTILE),
+            min_open_interest: read_env(
+                "MARKET_INT_MIN_OPEN_INTEREST",
+                constants::MIN_OPEN_INTEREST,
+            ),
+            risk_free_rate: read_env(
+                "MARKET_INT_RISK_FREE_RATE",
+                constants::DEFAULT_RISK_FREE_RATE,
+            ),
+            signal_rsi_sell: read_env("MARKET_INT_SIGNAL_RSI_SELL", constants::SIGNAL_RSI_SELL),
+            signal_rsi_warning: read_env(
+                "MARKET_INT_SIGNAL_RSI_WARNING",
+                constants::SIGNAL_RSI_WARNING,
+            ),
+            signal_min_sharpe: read_env(
+                "MARKET_INT_SIGNAL_MIN_SHARPE",
+                constants::SIGNAL_MIN_SHARPE,
+            ),
+            db_warn_size_mb: read_env("MARKET_INT_DB_WARN_SIZE_MB", constants::DB_WARN_SIZE_MB),
+        }
+    }
+}
+
+// Parses an environment variable, falling back to the default when it is unset or invalid.
+fn read_env<T: FromStr + Display>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|_| {
+            log::warn!("Invalid value {:?} for {}, using {}", value, name, default);
+            default
+        }),
+        Err(_) => default,
+    }
+}
@@ -1,3 +1,40 @@
 pub const CANDLE_COUNT: u32 = 250;
 pub const MIN_OPEN_INTEREST: u32 = 50;
+pub const MID_PRICE_TOLERANCE: f64 = 0.005;
 pub const PERCENTILE: f64 = 0.9;
+pub const HV_WINDOW_DAYS: u64 = 45;
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+pub const DAYS_PER_YEAR: f64 = 365.0;
+pub const DEFAULT_RISK_FREE_RATE: f64 = 0.04;
+pub const ROLLING_SHARPE_WINDOW: usize = 252;
+pub const VWAP_WINDOW: usize = 20;
+pub const MOMENTUM_PERIOD: u32 = 20;
+pub const INDICATOR_PERIOD: u32 = 20;
+pub const MOMENTUM_DOWNTREND_ROC: f64 = -0.10;
+pub const ATR_TREND_WINDOW: u32 = 10;
+pub const MAX_DROP_PERIOD: usize = 5;
+pub const MAX_DROP_ALL_PERIODS: [usize; 4] = [5, 10, 20, 40];
+pub const MAX_DROP_EMA_PERIOD: u32 = 4;
+pub const MAX_DROP_DISCREPANCY: f64 = 0.2;
+pub const DISCONTINUITY_THRESHOLD: f64 = 0.3;
+pub const ATR_PLOT_WIDTH: usize = 60;
+pub const ATR_PLOT_HEIGHT: usize = 10;
+pub const OI_CONCENTRATION_THRESHOLD: f64 = 0.20;
+pub const WATCH_INTERVAL_SECS: u64 = 900;
+pub const TELEGRAM_MAX_MESSAGE_LEN: usize = 4096;
+pub const OPTION_CHAIN_PAGE_SIZE: usize = 200;
+pub const DB_WARN_SIZE_MB: u64 = 500;
+pub const SQLITE_BUSY_RETRIES: u32 = 3;
+pub const SQLITE_BUSY_RETRY_DELAY_MS: u64 = 200;
+pub const MIN_DTE: u32 = 3;
+pub const MAX_DTE: u32 = 60;
+pub const EXPIRY_DTE_WINDOW_DAYS: u64 = 6;
+pub const CONTRACT_MULTIPLIER: f64 = 100.0;
+pub const RSI_PERIOD: usize = 14;
+pub const BOLLINGER_PERIOD: usize = 20;
+pub const BOLLINGER_STD_DEV: f64 = 2.0;
+pub const SIGNAL_CANDLE_COUNT: u32 = 100;
+pub const SIGNAL_RSI_SELL: f64 = 40.0;
+pub const SIGNAL_RSI_WARNING: f64 = 30.0;
+pub const SIGNAL_MIN_SHARPE: f64 = 0.5;
+pub const HV_30_CANDLE_COUNT: u32 = 31;
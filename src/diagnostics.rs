@@ -0,0 +1,90 @@
+use std::env;
+
+use telegram_bot_api::{bot, types::ChatId};
+
+use crate::{
+    marketdata::api_caller,
+    model::{self, QuotesError},
+    store::sqlite,
+};
+
+/// Environment variables every full run needs.
+const REQUIRED_ENV_VARS: [&str; 4] = [
+    "marketdata_token",
+    "telegram_bot_token",
+    "telegram_chat_id",
+    "sqlite_file",
+];
+
+/// Checks the environment, the MarketData API, the database and Telegram, printing one line
+/// per check. Returns whether every check passed.
+pub async fn test_connection() -> bool {
+    let mut passed = true;
+
+    let missing: Vec<&str> = REQUIRED_ENV_VARS
+        .into_iter()
+        .filter(|name| env::var(name).is_err())
+        .collect();
+    passed &= report(
+        "Environment variables",
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(format!("missing {}", missing.join(", "))),
+        },
+    );
+
+    passed &= report(
+        "MarketData API",
+        api_caller::market_status()
+            .await
+            .map(|status| log::info!("Market status: {:?}", status))
+            .map_err(|err| err.to_string()),
+    );
+
+    // `init_connection` panics when sqlite_file is unset.
+    let database = match env::var("sqlite_file") {
+        Ok(_) => sqlite::init_connection()
+            .map(|_| ())
+            .map_err(|err| err.to_string()),
+        Err(_) => Err("sqlite_file is not set".to_string()),
+    };
+    passed &= report("SQLite database", database);
+
+    passed &= report(
+        "Telegram",
+        send_telegram_message("Connection test successful")
+            .await
+            .map_err(|err| err.to_string()),
+    );
+
+    passed
+}
+
+// Prints the outcome of a check and returns whether it passed.
+fn report(check: &str, result: Result<(), String>) -> bool {
+    match result {
+        Ok(()) => {
+            println!("[ OK ] {}", check);
+            true
+        }
+        Err(err) => {
+            println!("[FAIL] {}: {}", check, err);
+            false
+        }
+    }
+}
+
+// Sends a plain text message to the Telegram chat.
+async fn send_telegram_message(text: &str) -> model::Result<()> {
+    let token = env::var("telegram_bot_token")?;
+    let chat_id = env::var("telegram_chat_id")?
+        .parse::<i64>()
+        .map_err(|_| QuotesError::EnvVarNotSet(env::VarError::NotPresent))?;
+    let bot = bot::BotApi::new(token, None).await?;
+    bot.send_message(telegram_bot_api::methods::SendMessage::new(
+        ChatId::IntType(chat_id),
+        text.to_string(),
+    ))
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use crate::http::client::{self, RequestError};
+use std::collections::HashMap;
+
+/// Posts a text message to a Discord webhook.
+pub async fn send_discord_message(webhook_url: &str, content: &str) -> Result<(), RequestError> {
+    // `wait=true` makes Discord return the created message instead of an empty body.
+    let resp = client::request::<serde_json::Value>(
+        client::Method::Post(Some(client::Body::Json(
+            serde_json::json!({ "content": content }),
+        ))),
+        webhook_url,
+        HashMap::from([("wait", "true")]),
+        HashMap::new(),
+        None,
+    )
+    .await?;
+    log::debug!("discord api return: {:?}", resp);
+    Ok(())
+}
+
+/// Uploads a file to a Discord webhook as a multipart form.
+pub async fn send_discord_file(
+    webhook_url: &str,
+    filename: &str,
+    data: &[u8],
+) -> Result<(), RequestError> {
+    let form = client::multipart_form(&[], ("files[0]", filename, data))?;
+    let resp = client::request::<serde_json::Value>(
+        client::Method::Post(Some(client::Body::Form(form))),
+        webhook_url,
+        HashMap::from([("wait", "true")]),
+        HashMap::new(),
+        None,
+    )
+    .await?;
+    log::debug!("discord api return: {:?}", resp);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    #[tokio::test]
+    async fn file_is_posted_as_multipart_form() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/webhook")
+            .match_query(Matcher::UrlEncoded("wait".into(), "true".into()))
+            .match_header(
+                "content-type",
+                Matcher::Regex("^multipart/form-data; boundary=".into()),
+            )
+            .match_body(Matcher::AllOf(vec![
+                Matcher::Regex(r#"name="files\[0\]"; filename="chains.csv""#.into()),
+                Matcher::Regex("underlying,strike".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id":"1"}"#)
+            .create_async()
+            .await;
+
+        let webhook_url = format!("{}/webhook", server.url());
+        send_discord_file(&webhook_url, "chains.csv", b"underlying,strike\nAAPL,100\n")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn message_is_posted_as_json_content() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/webhook")
+            .match_query(Matcher::UrlEncoded("wait".into(), "true".into()))
+            .match_body(Matcher::Json(serde_json::json!({ "content": "hello" })))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"id":"1"}"#)
+            .create_async()
+            .await;
+
+        let webhook_url = format!("{}/webhook", server.url());
+        send_discord_message(&webhook_url, "hello").await.unwrap();
+        mock.assert_async().await;
+    }
+}
@@ -1,7 +1,11 @@
 use crate::http::client::{self, RequestError};
+use crate::model;
 use serde::Deserialize;
 use std::{collections::HashMap, env};
 
+// Base URL for the Dropbox RPC endpoints.
+const API_URL: &str = "https://api.dropboxapi.com/2/";
+
 #[derive(Debug, Deserialize)]
 pub struct DropboxResp {
     pub is_downloadable: bool,
@@ -12,32 +16,239 @@ pub async fn upload_to_dropbox(content: &[u8], dropbox_path: &str) -> Result<(),
     let token = env::var("dropbox_token").map_err(|_| RequestError::TokenNotSet)?;
 
     let resp = client::request::<DropboxResp>(
-        client::Method::Post(Some(content.to_vec())),
+        client::Method::Post(Some(client::Body::bytes(content.to_vec()))),
         "https://content.dropboxapi.com/2/files/upload",
         HashMap::new(),
-        HashMap::from([
-            (
-                "Dropbox-API-Arg",
-                serde_json::json!({
-                    "autorename": false,
-                    "mode": "add",
-                    "mute": false,
-                    "path": dropbox_path,
-                    "strict_conflict": false
-                })
-                .to_string()
-                .as_str(),
-            ),
-            (
-                reqwest::header::CONTENT_TYPE.as_str(),
-                "application/octet-stream",
-            ),
-        ]),
+        HashMap::from([(
+            "Dropbox-API-Arg",
+            serde_json::json!({
+                "autorename": false,
+                "mode": "add",
+                "mute": false,
+                "path": dropbox_path,
+                "strict_conflict": false
+            })
+            .to_string()
+            .as_str(),
+        )]),
         Some(&token),
     )
     .await?;
 
-    log::debug!("dropbox api return: {:?}", resp);
+    log::info!(
+        path = dropbox_path, downloadable = resp.is_downloadable;
+        "Uploaded {} to Dropbox", resp.name
+    );
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct SharedLinkResp {
+    url: String,
+}
+
+/// Returns a public shared link to a file, creating one unless it already exists.
+pub async fn get_shared_link(path: &str) -> Result<String, RequestError> {
+    let token = env::var("dropbox_token").map_err(|_| RequestError::TokenNotSet)?;
+
+    let resp = post_json::<SharedLinkResp>(
+        API_URL,
+        "sharing/create_shared_link_with_settings",
+        serde_json::json!({
+            "path": path,
+            "settings": { "requested_visibility": "public" },
+        }),
+        &token,
+    )
+    .await;
+    match resp {
+        Ok(resp) => Ok(resp.url),
+        // An existing link is returned in the error body.
+        Err(RequestError::HttpError(url, 409, body)) => {
+            let existing: serde_json::Value = serde_json::from_str(&body)
+                .map_err(|_| RequestError::HttpError(url.clone(), 409, body.clone()))?;
+            existing["error"]["shared_link_already_exists"]["metadata"]["url"]
+                .as_str()
+                .map(String::from)
+                .ok_or(RequestError::HttpError(url, 409, body))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ListFolderResp {
+    entries: Vec<ListFolderEntry>,
+    cursor: String,
+    has_more: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListFolderEntry {
+    name: String,
+    path_display: Option<String>,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    server_modified: String,
+}
+
+/// Lists the files and folders directly inside a Dropbox folder.
+pub async fn list_files(folder_path: &str) -> Result<Vec<model::DropboxEntry>, RequestError> {
+    let token = env::var("dropbox_token").map_err(|_| RequestError::TokenNotSet)?;
+
+    // The API addresses the root folder as an empty path.
+    let folder_path = if folder_path == "/" { "" } else { folder_path };
+    let mut resp = post_json::<ListFolderResp>(
+        API_URL,
+        "files/list_folder",
+        serde_json::json!({ "path": folder_path }),
+        &token,
+    )
+    .await?;
+
+    let mut entries = Vec::new();
+    loop {
+        entries.extend(resp.entries.into_iter().map(|e| model::DropboxEntry {
+            path: e.path_display.unwrap_or_else(|| e.name.clone()),
+            name: e.name,
+            size: e.size,
+            modified: e.server_modified,
+        }));
+        if !resp.has_more {
+            break;
+        }
+        resp = post_json::<ListFolderResp>(
+            API_URL,
+            "files/list_folder/continue",
+            serde_json::json!({ "cursor": resp.cursor }),
+            &token,
+        )
+        .await?;
+    }
 
+    Ok(entries)
+}
+
+/// Deletes a file or folder. A `*` in the last path component deletes every entry of the
+/// parent folder whose name matches the pattern.
+pub async fn delete_file(path: &str) -> Result<(), RequestError> {
+    let token = env::var("dropbox_token").map_err(|_| RequestError::TokenNotSet)?;
+
+    let (folder, pattern) = path.rsplit_once('/').unwrap_or(("", path));
+    if !pattern.contains('*') {
+        return delete_path(API_URL, path, &token).await;
+    }
+
+    let folder = if folder.is_empty() { "/" } else { folder };
+    for entry in list_files(folder).await? {
+        if wildcard_match(pattern, &entry.name) {
+            delete_path(API_URL, &entry.path, &token).await?;
+        }
+    }
     Ok(())
 }
+
+// Deletes a single path through the API at `api_url`.
+async fn delete_path(api_url: &str, path: &str, token: &str) -> Result<(), RequestError> {
+    let resp = post_json::<serde_json::Value>(
+        api_url,
+        "files/delete_v2",
+        serde_json::json!({ "path": path }),
+        token,
+    )
+    .await?;
+    log::info!(path = path; "Deleted from Dropbox");
+    log::debug!("dropbox api return: {:?}", resp);
+    Ok(())
+}
+
+// Matches a name against a pattern where `*` stands for any run of characters.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+// Posts a JSON body to an RPC endpoint of the API at `api_url`.
+async fn post_json<T: serde::de::DeserializeOwned>(
+    api_url: &str,
+    endpoint: &str,
+    body: serde_json::Value,
+    token: &str,
+) -> Result<T, RequestError> {
+    client::request::<T>(
+        client::Method::Post(Some(client::Body::Json(body))),
+        format!("{}{}", api_url, endpoint).as_str(),
+        HashMap::new(),
+        HashMap::new(),
+        Some(token),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    #[tokio::test]
+    async fn delete_path_posts_the_path_with_the_token() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/files/delete_v2")
+            .match_header("authorization", "Bearer test-token")
+            .match_body(Matcher::Json(
+                serde_json::json!({ "path": "/reports/a.csv" }),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"metadata":{"name":"a.csv"}}"#)
+            .create_async()
+            .await;
+
+        let api_url = format!("{}/", server.url());
+        delete_path(&api_url, "/reports/a.csv", "test-token")
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn delete_path_fails_on_an_error_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/files/delete_v2")
+            .with_status(409)
+            .with_body("path_lookup/not_found")
+            .create_async()
+            .await;
+
+        let api_url = format!("{}/", server.url());
+        let err = delete_path(&api_url, "/missing.csv", "test-token")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::HttpError(_, 409, _)));
+    }
+
+    #[test]
+    fn wildcards_match_any_run_of_characters() {
+        assert!(wildcard_match("*.csv", "20261018_0930.csv"));
+        assert!(wildcard_match("2026*_0930.csv", "20261018_0930.csv"));
+        assert!(!wildcard_match("*.csv", "report.txt"));
+        assert!(wildcard_match("report.csv", "report.csv"));
+    }
+}
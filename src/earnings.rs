@@ -0,0 +1,51 @@
+use crate::model;
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// A row of the earnings calendar.
+#[derive(Debug, Deserialize)]
+struct EarningsRow {
+    symbol: String,
+    earnings_timestamp: u32,
+}
+
+/// Reads a CSV of `symbol,earnings_timestamp` rows into the upcoming earnings timestamps of
+/// each symbol, sorted. Events that already happened are dropped.
+pub fn load_earnings_calendar(path: &str) -> model::Result<HashMap<String, Vec<u32>>> {
+    let now = Local::now().timestamp() as u32;
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut earnings_map: HashMap<String, Vec<u32>> = HashMap::new();
+    for row in reader.deserialize::<EarningsRow>() {
+        let row = row?;
+        if row.earnings_timestamp < now {
+            continue;
+        }
+        earnings_map
+            .entry(row.symbol)
+            .or_default()
+            .push(row.earnings_timestamp);
+    }
+    for timestamps in earnings_map.values_mut() {
+        timestamps.sort_unstable();
+    }
+    Ok(earnings_map)
+}
+
+/// Whether the symbol has an earnings event at or before `expiry_ts`.
+pub fn has_earnings_before_expiration(
+    symbol: &str,
+    expiry_ts: u32,
+    earnings_map: &HashMap<String, Vec<u32>>,
+) -> bool {
+    earnings_map
+        .get(symbol)
+        .is_some_and(|timestamps| timestamps.iter().any(|&ts| ts <= expiry_ts))
+}
+
+/// The end of an expiration day formatted as "%Y-%m-%d", as a UTC timestamp.
+pub fn expiration_timestamp(expiration: &str) -> Option<u32> {
+    let date = NaiveDate::parse_from_str(expiration, "%Y-%m-%d").ok()?;
+    let end_of_day = date.and_hms_opt(23, 59, 59)?;
+    Some(end_of_day.and_utc().timestamp() as u32)
+}
@@ -0,0 +1,101 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    store::{candle, risk_metrics, rolling_sharpe, sqlite},
+    symbols,
+};
+use flate2::{write::GzEncoder, Compression};
+use rusqlite::Connection;
+use serde::Serialize;
+use std::{fs, fs::File, path::Path};
+
+// A row of the Sharpe export. Missing metrics are written as empty cells.
+#[derive(Serialize)]
+struct SharpeRow {
+    symbol: String,
+    sharpe: Option<f64>,
+    sortino: Option<f64>,
+    timestamp: Option<u32>,
+}
+
+/// Exports every table of the database to `<output_dir>/<table>.csv`. With `compress`, the
+/// directory is then packed into `<output_dir>.tar.gz`. Returns the total number of rows.
+pub fn export_database(
+    conn: &Connection, // Database connection.
+    output_dir: &str,  // Directory to write the CSV files to.
+    compress: bool,
+) -> model::Result<u64> {
+    fs::create_dir_all(output_dir)?;
+
+    let mut total = 0;
+    for table in sqlite::get_table_names(conn)? {
+        let path = Path::new(output_dir).join(format!("{}.csv", table));
+        let rows = sqlite::export_table_to_csv(conn, &table, &path)?;
+        log::info!(table = table.as_str(), rows = rows; "Exported table to {}", path.display());
+        total += rows;
+    }
+
+    if compress {
+        let archive_path = format!("{}.tar.gz", output_dir.trim_end_matches('/'));
+        let encoder = GzEncoder::new(File::create(&archive_path)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        let name = Path::new(output_dir)
+            .file_name()
+            .unwrap_or_else(|| "export".as_ref());
+        archive.append_dir_all(name, output_dir)?;
+        archive.into_inner()?.finish()?;
+        log::info!("Compressed export to {}", archive_path);
+    }
+
+    Ok(total)
+}
+
+/// Writes the latest Sharpe ratio and the Sortino ratio over the configured candles of each
+/// symbol to a CSV file, sorted by Sharpe ratio with the highest first. Returns the number of rows.
+pub fn export_sharpe(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &Connection,       // Database connection.
+    output_path: &str,       // CSV file to write.
+    config: &Config,
+) -> model::Result<usize> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    candle::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+
+    let mut rows = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let latest = rolling_sharpe::get_latest_rolling_sharpe_entry(
+            conn,
+            &symbol,
+            constants::ROLLING_SHARPE_WINDOW,
+        )
+        .with_symbol(&symbol)?;
+        let risk = risk_metrics::get_risk_metrics(
+            conn,
+            &symbol,
+            config.candle_count,
+            config.risk_free_rate,
+        )
+        .with_symbol(&symbol)?;
+        rows.push(SharpeRow {
+            symbol,
+            sharpe: latest.map(|(_, sharpe)| sharpe),
+            sortino: risk.sortino,
+            timestamp: latest.map(|(timestamp, _)| timestamp),
+        });
+    }
+    // Symbols without a Sharpe ratio go last.
+    rows.sort_by(|a, b| match (a.sharpe, b.sharpe) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+
+    let mut writer = csv::Writer::from_path(output_path)?;
+    for row in &rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(rows.len())
+}
@@ -1,11 +1,48 @@
+use crate::metrics;
 use reqwest::{self, RequestBuilder};
 use serde::de::DeserializeOwned;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, env, sync::Arc, time::Duration};
 use thiserror::Error;
 
+// Default overall request timeout in seconds.
+const DEFAULT_TIMEOUT_SECS: u64 = 30;
+// Default connection timeout in seconds.
+const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 10;
+// Wait assumed for a 429 response without a Retry-After header, in seconds.
+const DEFAULT_RETRY_AFTER_SECS: u64 = 60;
+
 // Shared HTTP client instance.
 lazy_static::lazy_static! {
-    static ref CLIENT: Arc<reqwest::Client> = Arc::new(reqwest::Client::new());
+    static ref CLIENT: Arc<reqwest::Client> = Arc::new(build_http_client(
+        env_secs("HTTP_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS),
+        env_secs("HTTP_CONNECT_TIMEOUT_SECS", DEFAULT_CONNECT_TIMEOUT_SECS),
+    ));
+}
+
+// Reads a number of seconds from an environment variable, falling back to the default.
+fn env_secs(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Builds an HTTP client with the given overall and connection timeouts.
+pub fn build_http_client(timeout_secs: u64, connect_timeout_secs: u64) -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .connect_timeout(Duration::from_secs(connect_timeout_secs))
+        .build()
+        .expect("Failed to build HTTP client")
+}
+
+// Maps a reqwest error, keeping timeouts distinct from other failures.
+fn map_reqwest_error(e: reqwest::Error) -> RequestError {
+    if e.is_timeout() {
+        RequestError::Timeout(e.to_string())
+    } else {
+        RequestError::Other(e.to_string())
+    }
 }
 
 /// Custom error type for HTTP requests.
@@ -17,17 +54,35 @@ pub enum RequestError {
     HttpError(reqwest::Url, u16, String),
     #[error("Error deserializing JSON: {0}")]
     JsonError(String),
+    #[error("Request timed out: {0}")]
+    Timeout(String),
+    #[error("Rate limit exceeded, retry after {retry_after_secs}s")]
+    RateLimited { retry_after_secs: u64 },
     #[error("Other error: {0}")]
     Other(String),
 }
 
 pub enum Method {
     Get,
-    Put(Option<Vec<u8>>),
-    Post(Option<Vec<u8>>),
+    Put(Option<Body>),
+    Post(Option<Body>),
     Delete,
 }
 
+/// Request body, sent with the matching content type.
+pub enum Body {
+    Json(serde_json::Value),
+    Bytes(Vec<u8>, &'static str), // Raw bytes and their content type.
+    Form(reqwest::multipart::Form),
+}
+
+impl Body {
+    /// Raw bytes sent as `application/octet-stream`.
+    pub fn bytes(data: Vec<u8>) -> Body {
+        Body::Bytes(data, "application/octet-stream")
+    }
+}
+
 /// Makes an HTTP request to the specified path with optional parameters.
 pub async fn request<T: DeserializeOwned>(
     method: Method,
@@ -36,6 +91,73 @@ pub async fn request<T: DeserializeOwned>(
     headers: HashMap<&str, &str>, // Optional header parameters.
     token: Option<&str>,
 ) -> Result<T, RequestError> {
+    // Deserialize the JSON response.
+    send(method, path, params, headers, token)
+        .await?
+        .json()
+        .await
+        .map_err(|e| {
+            if e.is_timeout() {
+                RequestError::Timeout(e.to_string())
+            } else {
+                RequestError::JsonError(e.to_string())
+            }
+        })
+}
+
+/// Makes an HTTP request like `request`, returning the response body as text.
+/// For APIs that do not answer with JSON.
+pub async fn request_text(
+    method: Method,
+    path: &str,                   // API path.
+    params: HashMap<&str, &str>,  // Optional query parameters.
+    headers: HashMap<&str, &str>, // Optional header parameters.
+    token: Option<&str>,
+) -> Result<String, RequestError> {
+    send(method, path, params, headers, token)
+        .await?
+        .text()
+        .await
+        .map_err(map_reqwest_error)
+}
+
+/// Builds a multipart form from text fields and one file.
+pub fn multipart_form(
+    fields: &[(&str, &str)],   // (name, value) text fields.
+    file: (&str, &str, &[u8]), // (field name, file name, contents).
+) -> Result<reqwest::multipart::Form, RequestError> {
+    let (field_name, filename, data) = file;
+    let mut form = reqwest::multipart::Form::new();
+    for (name, value) in fields {
+        form = form.text(name.to_string(), value.to_string());
+    }
+    let part = reqwest::multipart::Part::bytes(data.to_vec())
+        .file_name(filename.to_string())
+        .mime_str("application/octet-stream")
+        .map_err(map_reqwest_error)?;
+    Ok(form.part(field_name.to_string(), part))
+}
+
+// Attaches the body, if any, along with its content type.
+fn with_body(req: RequestBuilder, body: Option<Body>) -> RequestBuilder {
+    match body {
+        Some(Body::Json(value)) => req.json(&value),
+        Some(Body::Bytes(data, content_type)) => req
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(data),
+        Some(Body::Form(form)) => req.multipart(form),
+        None => req,
+    }
+}
+
+// Sends the request and fails on non-success status codes.
+async fn send(
+    method: Method,
+    path: &str,
+    params: HashMap<&str, &str>,
+    headers: HashMap<&str, &str>,
+    token: Option<&str>,
+) -> Result<reqwest::Response, RequestError> {
     // Construct the URL.
     let url = if !params.is_empty() {
         reqwest::Url::parse_with_params(path, &params)
@@ -49,18 +171,8 @@ pub async fn request<T: DeserializeOwned>(
     match method {
         Method::Get => req = CLIENT.get(url.as_str()),
         Method::Delete => req = CLIENT.delete(url.as_str()),
-        Method::Post(body) => {
-            req = CLIENT.post(url.as_str());
-            if let Some(body) = body {
-                req = req.body(body);
-            }
-        }
-        Method::Put(body) => {
-            req = CLIENT.put(url.as_str());
-            if let Some(body) = body {
-                req = req.body(body);
-            }
-        }
+        Method::Post(body) => req = with_body(CLIENT.post(url.as_str()), body),
+        Method::Put(body) => req = with_body(CLIENT.put(url.as_str()), body),
     }
 
     if let Some(token) = token {
@@ -73,26 +185,91 @@ pub async fn request<T: DeserializeOwned>(
         }
     }
 
-    let response = req
-        .send()
-        .await
-        .map_err(|e| RequestError::Other(e.to_string()))?;
+    // Calls are labelled by API host.
+    let module = url.host_str().unwrap_or_default().to_string();
+    let timer = metrics::API_CALL_DURATION_SECONDS
+        .with_label_values(&[&module])
+        .start_timer();
+    let response = req.send().await;
+    timer.observe_duration();
+    let response = response.map_err(|e| {
+        metrics::API_CALLS_TOTAL
+            .with_label_values(&[&module, "error"])
+            .inc();
+        map_reqwest_error(e)
+    })?;
 
     // Get the response status code.
     let status = response.status();
+    metrics::API_CALLS_TOTAL
+        .with_label_values(&[&module, status.as_str()])
+        .inc();
+
+    // Rate limited responses say how long to wait in the Retry-After header.
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let retry_after_secs = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_RETRY_AFTER_SECS);
+        return Err(RequestError::RateLimited { retry_after_secs });
+    }
 
     // Handle non-success status codes.
     if !status.is_success() {
-        let body = response
-            .text()
-            .await
-            .map_err(|e| RequestError::Other(e.to_string()))?;
+        let body = response.text().await.map_err(map_reqwest_error)?;
         return Err(RequestError::HttpError(url, status.as_u16(), body));
     }
 
-    // Deserialize the JSON response.
-    response
-        .json()
-        .await
-        .map_err(|e| RequestError::JsonError(e.to_string()))
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::QuotesError;
+
+    async fn rate_limited_request(retry_after: Option<&str>) -> RequestError {
+        let mut server = mockito::Server::new_async().await;
+        let mut mock = server
+            .mock("GET", "/quotes")
+            .with_status(429)
+            .with_body(r#"{"s":"error","errmsg":"Too many requests"}"#);
+        if let Some(retry_after) = retry_after {
+            mock = mock.with_header("retry-after", retry_after);
+        }
+        mock.create_async().await;
+
+        let url = format!("{}/quotes", server.url());
+        request_text(Method::Get, &url, HashMap::new(), HashMap::new(), None)
+            .await
+            .unwrap_err()
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_maps_to_rate_limit_exceeded() {
+        let err = rate_limited_request(Some("30")).await;
+        assert!(matches!(
+            err,
+            RequestError::RateLimited {
+                retry_after_secs: 30
+            }
+        ));
+        assert!(matches!(
+            QuotesError::from(err),
+            QuotesError::RateLimitExceeded {
+                retry_after_secs: 30
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_without_retry_after_waits_the_default() {
+        let err = rate_limited_request(None).await;
+        assert!(matches!(
+            err,
+            RequestError::RateLimited { retry_after_secs } if retry_after_secs == DEFAULT_RETRY_AFTER_SECS
+        ));
+    }
 }
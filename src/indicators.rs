@@ -0,0 +1,167 @@
+use crate::{
+    config::Config,
+    model::{self, WithSymbol},
+    signal, statistics,
+    store::{candle, indicators},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Indicators that can be calculated.
+pub const INDICATORS: [&str; 3] = ["sma", "ema", "rsi"];
+
+/// Calculates the given indicators of every symbol's closes over `period` candles and saves
+/// them to the database. Fails before reading any symbol when a name is not in `INDICATORS`.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    names: &[String],        // Indicators to calculate, from INDICATORS.
+    period: u32,             // Number of candles each indicator looks back.
+    config: &Config,
+) -> model::Result<()> {
+    // Rejected before any work, as the CLI's value parser would.
+    if let Some(name) = names
+        .iter()
+        .find(|name| !INDICATORS.contains(&name.as_str()))
+    {
+        return Err(model::QuotesError::InvalidConfig(format!(
+            "unknown indicator {}, expected one of {}",
+            name,
+            INDICATORS.join(", ")
+        )));
+    }
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    indicators::create_table(conn)?;
+
+    let mut values = Vec::with_capacity(symbols.len() * names.len());
+    for symbol in symbols {
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let Some(last_candle) = candles.last() else {
+            log::warn!(symbol = symbol.as_str(); "No candles, skipping indicators");
+            continue;
+        };
+        for name in names {
+            let value = match name.as_str() {
+                "sma" => calculate_sma(&candles, period),
+                "ema" => calculate_ema_close(&candles, period),
+                "rsi" => calculate_rsi(&candles, period),
+                _ => unreachable!("indicator names are checked before the loop"),
+            };
+            match value {
+                Ok(value) => {
+                    values.push((symbol.clone(), name.clone(), value, last_candle.timestamp))
+                }
+                Err(err) => {
+                    log::warn!(symbol = symbol.as_str(), indicator = name.as_str(), err:% = err; "Skipping indicator")
+                }
+            }
+        }
+    }
+
+    indicators::save_indicators(conn, period, &values)?;
+    Ok(())
+}
+
+/// Simple moving average of the last `period` closes.
+/// Candles must be sorted from oldest to newest.
+pub fn calculate_sma(candles: &[model::Candle], period: u32) -> model::Result<f64> {
+    let period = period as usize;
+    if period == 0 || candles.len() < period {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for SMA calculation (period: {}, candles: {})",
+                period,
+                candles.len()
+            ),
+        ));
+    }
+    let closes = &candles[candles.len() - period..];
+    Ok(closes.iter().map(|c| c.close).sum::<f64>() / period as f64)
+}
+
+/// Exponential moving average of the closes with a smoothing period of `period` candles.
+/// Candles must be sorted from oldest to newest.
+pub fn calculate_ema_close(candles: &[model::Candle], period: u32) -> model::Result<f64> {
+    if period == 0 {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for EMA calculation (period: {}, candles: {})",
+                period,
+                candles.len()
+            ),
+        ));
+    }
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    statistics::exponential_moving_average(&closes, period)
+}
+
+// Relative strength index of the closes over `period` candles.
+fn calculate_rsi(candles: &[model::Candle], period: u32) -> model::Result<f64> {
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    signal::rsi(&closes, period as usize).ok_or_else(|| {
+        model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for RSI calculation (period: {}, candles: {})",
+                period,
+                candles.len()
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candles(closes: &[f64]) -> Vec<model::Candle> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| model::Candle {
+                symbol: "TEST".to_string(),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 100,
+                timestamp: i as u32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sma_averages_the_last_period_closes() {
+        let candles = candles(&[1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(calculate_sma(&candles, 2).unwrap(), 3.5);
+        assert!(calculate_sma(&candles, 0).is_err());
+        assert!(calculate_sma(&candles, 5).is_err());
+    }
+
+    #[test]
+    fn ema_rejects_a_period_of_zero() {
+        let candles = candles(&[1.0, 2.0, 3.0]);
+        assert!(calculate_ema_close(&candles, 0).is_err());
+    }
+
+    #[test]
+    fn ema_of_constant_closes_is_the_close() {
+        let candles = candles(&[5.0; 10]);
+        assert!((calculate_ema_close(&candles, 3).unwrap() - 5.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unknown_indicator_is_rejected_before_any_work() {
+        let mut conn = crate::store::sqlite::test_conn();
+        let names = ["sma".to_string(), "macd".to_string()];
+        let config = Config::from_env();
+        let result = calculate_and_save("missing-symbols.txt", &mut conn, &names, 3, &config);
+        assert!(
+            matches!(result, Err(model::QuotesError::InvalidConfig(msg)) if msg.contains("macd"))
+        );
+    }
+}
@@ -0,0 +1,112 @@
+// Library of the market data application, shared by the command-line binary and the
+// integration tests.
+pub mod marketdata {
+    // Client for fetching market data.
+    pub mod api_caller;
+    // Response structures for market data.
+    pub mod response;
+}
+// HTTP client module.
+pub mod http {
+    // HTTP client implementation.
+    pub mod client;
+}
+/// Configuration and connectivity checks.
+pub mod diagnostics;
+/// Discord webhook notifications.
+pub mod discord;
+/// Dropbox file storage.
+pub mod dropbox;
+/// Database export to CSV.
+pub mod export;
+// Data models.
+pub mod model;
+// Pull quotes from API.
+pub mod quotes;
+// Average True Range (ATR) calculation.
+pub mod atr;
+/// Put-selling backtest over stored option chains.
+pub mod backtest;
+/// Black-Scholes option pricing and implied volatility.
+pub mod black_scholes;
+/// Compound annual growth rate calculation.
+pub mod cagr;
+/// Earnings calendar checks.
+pub mod earnings;
+/// Moving average and RSI indicators of the close.
+pub mod indicators;
+/// Max drop calculation.
+pub mod maxdrop;
+/// Rate of change momentum calculation.
+pub mod momentum;
+/// Pull option chains from API based on ATR retrieved from database.
+pub mod option;
+/// The PerformAll pipeline.
+pub mod pipeline;
+/// ASCII charts for the log output.
+pub mod plot;
+/// Option position summary.
+pub mod portfolio;
+/// Sharpe ratio calculation.
+pub mod sharpe;
+/// Rule-based trading signals.
+pub mod signal;
+/// Slack notifications.
+pub mod slack;
+/// Shared statistics: percentile and exponential moving average.
+pub mod statistics;
+/// Straddle implied move estimation.
+pub mod straddle;
+/// Drawdown duration calculation.
+pub mod streaks;
+/// module to read symbols from symbol file
+pub mod symbols;
+/// Telegram caption templates.
+pub mod template;
+/// Historical volatility calculation.
+pub mod volatility;
+/// Volume-weighted average price calculation.
+pub mod vwap;
+// Data storage module.
+pub mod store {
+    /// compound annual growth rate storage.
+    pub mod cagr;
+    /// Candle data storage.
+    pub mod candle;
+    /// implied move storage.
+    pub mod implied_move;
+    /// close indicator storage.
+    pub mod indicators;
+    /// max drop storage.
+    pub mod max_drop;
+    /// rate of change storage.
+    pub mod momentum;
+    /// option range storage.
+    pub mod option_chain;
+    /// recovery factor storage.
+    pub mod recovery_factor;
+    /// combined risk metrics lookup.
+    pub mod risk_metrics;
+    /// rolling Sharpe ratio storage.
+    pub mod rolling_sharpe;
+    /// option chain run storage.
+    pub mod runs;
+    /// Sortino ratio storage.
+    pub mod sortino;
+    /// SQLite database interaction.
+    pub mod sqlite;
+    /// drawdown duration storage.
+    pub mod streaks;
+    /// true range storage.
+    pub mod true_range;
+    /// rolling VWAP storage.
+    pub mod vwap;
+}
+/// Runtime configuration read from the environment.
+pub mod config;
+// module storing defaults
+pub mod constants;
+/// Logger initialisation.
+pub mod logging;
+/// Prometheus metrics.
+pub mod metrics;
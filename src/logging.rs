@@ -0,0 +1,39 @@
+use std::{env, io::Write};
+
+use chrono::Local;
+use log::kv::{Error, Key, Value, VisitSource};
+use serde_json::{Map, Value as JsonValue};
+
+// Collects the structured key-values of a record into a JSON object.
+struct JsonVisitor<'a>(&'a mut Map<String, JsonValue>);
+
+impl<'kvs> VisitSource<'kvs> for JsonVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), Error> {
+        self.0
+            .insert(key.to_string(), JsonValue::String(value.to_string()));
+        Ok(())
+    }
+}
+
+/// Initializes the global logger from `RUST_LOG`.
+/// Emits one JSON object per line when `LOG_FORMAT=json`, otherwise the default env_logger format.
+pub fn init() {
+    let mut builder = env_logger::Builder::from_default_env();
+
+    if env::var("LOG_FORMAT").is_ok_and(|format| format.eq_ignore_ascii_case("json")) {
+        builder.format(|buf, record| {
+            let mut line = Map::new();
+            line.insert("timestamp".into(), Local::now().to_rfc3339().into());
+            line.insert("level".into(), record.level().as_str().into());
+            line.insert("target".into(), record.target().into());
+            line.insert("message".into(), record.args().to_string().into());
+            record
+                .key_values()
+                .visit(&mut JsonVisitor(&mut line))
+                .map_err(std::io::Error::other)?;
+            writeln!(buf, "{}", JsonValue::Object(line))
+        });
+    }
+
+    builder.init();
+}
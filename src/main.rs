@@ -1,41 +1,12 @@
 // Main function for the market data application.
-mod marketdata {
-    // Client for fetching market data.
-    pub mod api_caller;
-    // Response structures for market data.
-    pub mod response;
-}
-// HTTP client module.
-mod http {
-    // HTTP client implementation.
-    pub mod client;
-}
-// Data models.
-mod model;
-// Pull quotes from API.
-mod quotes;
-// Average True Range (ATR) calculation.
-mod atr;
-/// Pull option chains from API based on ATR retrieved from database.
-mod option;
-/// module to read symbols from symbol file
-mod symbols;
-// Data storage module.
-mod store {
-    /// Candle data storage.
-    pub mod candle;
-    /// option range storage.
-    pub mod option_chain;
-    /// SQLite database interaction.
-    pub mod sqlite;
-    /// true range storage.
-    pub mod true_range;
-}
-// module storing defaults
-mod constants;
-
-use clap::{Parser, Subcommand};
+use chrono::NaiveDate;
+use clap::{builder::TypedValueParser, Parser, Subcommand};
 use dotenv::dotenv;
+use market_int::{
+    atr, backtest, cagr, config, constants, diagnostics, dropbox, export, indicators, logging,
+    maxdrop, metrics, model, momentum, option, pipeline, portfolio, quotes, sharpe, signal, store,
+    straddle, streaks, symbols, vwap,
+};
 
 // Command-line argument parser.
 #[derive(Parser, Debug)]
@@ -49,13 +20,300 @@ struct Args {
 #[derive(Subcommand, Debug)]
 enum Commands {
     // Pull quotes for specified symbols.
-    PullQuotes { symbols_file_path: String },
+    PullQuotes {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        // Pull even when the market is closed.
+        #[arg(long)]
+        force: bool,
+        // Candle resolution to pull.
+        #[arg(long, value_enum, default_value_t)]
+        period: model::Period,
+        // Only pull candles after this date (YYYY-MM-DD).
+        #[arg(long)]
+        since: Option<NaiveDate>,
+    },
     // Pull option chain data.
-    PullOptionChain { symbols_file_path: String },
+    PullOptionChain {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        // Option side to pull: call, put or both.
+        // The fully qualified path keeps clap from treating the Vec as a repeated argument, so
+        // the whole Vec is the one value produced by parse_sides.
+        #[arg(long, default_value = "put", value_parser = option::parse_sides)]
+        side: ::std::vec::Vec<model::OptionChainSide>,
+        #[command(flatten)]
+        alerts: option::AlertConfig,
+        #[command(flatten)]
+        filter: option::OptionChainFilterConfig,
+    },
     // Publish option chain to telegram.
-    PublishOptionChain { symbols_file_path: String },
-    PerformAll { symbols_file_path: String },
-    CalculateAtr { symbols_file_path: String },
+    PublishOptionChain {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[command(flatten)]
+        alerts: option::AlertConfig,
+        // Order the published chains by this column.
+        #[arg(long, value_enum)]
+        sort_by: Option<option::SortBy>,
+        // Publish only the put with the highest rate of return of each symbol.
+        #[arg(long)]
+        best_per_symbol: bool,
+        // Lowest rate of return a best put may have.
+        #[arg(long, default_value_t = 0.0, requires = "best_per_symbol")]
+        min_rate_of_return: f64,
+    },
+    PerformAll {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[command(flatten)]
+        alerts: option::AlertConfig,
+        #[command(flatten)]
+        filter: option::OptionChainFilterConfig,
+        // Serve Prometheus metrics on METRICS_PORT while running.
+        #[arg(long)]
+        metrics: bool,
+        // Pull quotes even when the market is closed.
+        #[arg(long)]
+        force: bool,
+        #[command(flatten)]
+        steps: pipeline::PipelineSteps,
+    },
+    // Run the PerformAll pipeline on a schedule until stopped.
+    WatchMode {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[command(flatten)]
+        schedule: pipeline::WatchSchedule,
+        #[command(flatten)]
+        alerts: option::AlertConfig,
+        #[command(flatten)]
+        filter: option::OptionChainFilterConfig,
+        #[command(flatten)]
+        steps: pipeline::PipelineSteps,
+    },
+    CalculateAtr {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long, value_enum, default_value_t = atr::AtrMode::Ratio)]
+        mode: atr::AtrMode,
+        // Do not log the EMA ATR history charts.
+        #[arg(long)]
+        no_plot: bool,
+        // Fail when a candle opens more than 30% away from the previous close.
+        #[arg(long)]
+        halt_on_discontinuity: bool,
+    },
+    // Calculate the rolling Sharpe ratio over a window of candles.
+    CalculateRollingSharpe {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long = "rolling-window", default_value_t = constants::ROLLING_SHARPE_WINDOW)]
+        window: usize,
+    },
+    // Calculate the Sharpe ratio of selling the stored puts cash-secured.
+    CalculateStrategySharpe {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+    },
+    // Calculate the rolling volume-weighted average price.
+    CalculateVwap {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long, default_value_t = constants::VWAP_WINDOW, value_parser = clap::value_parser!(u64).range(1..).map(|w| w as usize))]
+        window: usize,
+    },
+    // Calculate the largest price drops over windows of candles.
+    CalculateMaxDrop {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long, default_value_t = constants::MAX_DROP_PERIOD)]
+        period: usize,
+        // Also use every overlapping window.
+        #[arg(long)]
+        rolling: bool,
+        // Calculate every period of MAX_DROP_ALL_PERIODS instead.
+        #[arg(long, conflicts_with_all = ["period", "rolling"])]
+        all_periods: bool,
+    },
+    // Calculate the rate of change of the close over a period.
+    CalculateMomentum {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long, default_value_t = constants::MOMENTUM_PERIOD)]
+        period: u32,
+    },
+    // Calculate the compound annual growth rate of the close.
+    CalculateCompoundedReturn {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+    },
+    // Calculate moving averages and RSI of the close.
+    CalculateIndicators {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        // Comma-separated indicators to calculate.
+        #[arg(long, value_delimiter = ',', value_parser = indicators::INDICATORS, default_value = "sma,ema")]
+        indicators: Vec<String>,
+        #[arg(long, default_value_t = constants::INDICATOR_PERIOD, value_parser = clap::value_parser!(u32).range(1..))]
+        period: u32,
+    },
+    // Estimate the implied move from the at-the-money straddle of the latest option chain.
+    CalculateImpliedMove {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        warn_high_iv_move: Option<f64>,
+    },
+    // Calculate the maximum drawdown duration.
+    CalculateStreaks {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        warn_dd_duration_days: Option<u32>,
+    },
+    // Calculate the recovery factor (total return over maximum drawdown).
+    CalculateRecoveryFactor {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+    },
+    // Print the resolved configuration.
+    PrintConfig,
+    // Load candles from a CSV file with columns symbol,open,high,low,close,volume,timestamp.
+    ImportCandles {
+        csv_path: String,
+        // Only import the rows of this symbol.
+        #[arg(long)]
+        symbol: Option<String>,
+    },
+    // Delete every row of a table.
+    ClearTable {
+        table_name: String,
+        // Reclaim the freed space afterwards.
+        #[arg(long)]
+        vacuum: bool,
+    },
+    // Checkpoint the WAL file into the database.
+    Checkpoint {
+        #[arg(default_value = "PASSIVE", value_parser = store::sqlite::CHECKPOINT_MODES, ignore_case = true)]
+        mode: String,
+    },
+    // Check the environment variables, the MarketData API, the database and Telegram.
+    TestConnection,
+    // Check the database for corruption, missing tables and symbols with missing data.
+    CheckDatabase,
+    // Print the row count and estimated size of each table.
+    DatabaseStats {
+        // Also print a summary of the candles of each symbol.
+        #[arg(long)]
+        candles: bool,
+    },
+    // Dump every table to a CSV file in a directory.
+    ExportDatabase {
+        output_dir: String,
+        // Also pack the directory into a .tar.gz archive.
+        #[arg(long)]
+        compress: bool,
+    },
+    // Write the latest Sharpe and Sortino ratios of each symbol to a CSV file.
+    ExportSharpe {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        output_path: String,
+    },
+    // Print a stored option chain snapshot as CSV, the latest when no timestamp is given.
+    RetrieveOptionChain {
+        symbol: String,
+        timestamp: Option<String>,
+        #[arg(long)]
+        run_id: Option<String>,
+        #[command(flatten)]
+        filter: option::StrikeFilter,
+    },
+    // Write the symbols with enough candles and a high enough Sharpe ratio to a new file.
+    FilterSymbols {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        output_file_path: String,
+        #[arg(long, default_value_t = 0)]
+        min_candles: u32,
+        #[arg(long)]
+        min_sharpe: Option<f64>,
+    },
+    // Delete stored option chains of the symbols, optionally only snapshots before a date.
+    ClearOptionChain {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        before: Option<String>,
+    },
+    // List the files in a Dropbox folder.
+    ListDropbox {
+        folder_path: String,
+    },
+    // Replay stored option chain snapshots, selling puts near a target delta.
+    Backtest {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+        #[arg(long)]
+        target_delta: f64,
+        #[arg(long, default_value_t = 7)]
+        dte: u32,
+    },
+    // List the symbols of a symbols file, or those with data in the database.
+    ListSymbols {
+        symbols_file_path: Option<String>,
+        // List the symbols with candles or max drops stored instead.
+        #[arg(long, required_unless_present = "symbols_file_path")]
+        from_db: bool,
+    },
+    // Print the put-selling signal of each symbol.
+    GenerateSignals {
+        #[command(flatten)]
+        symbols: symbols::SymbolsInput,
+    },
+    // Summarise the option positions listed in a CSV file.
+    PortfolioSummary {
+        positions_file: String,
+        #[arg(long, value_enum, default_value_t = portfolio::SummaryFormat::Text)]
+        format: portfolio::SummaryFormat,
+    },
+    // Delete a Dropbox file, or every file matching a `*` pattern in its name.
+    DeleteDropboxFile {
+        path: String,
+    },
+}
+
+impl Commands {
+    // Symbols input of the commands that read a symbols file.
+    fn symbols(&self) -> Option<&symbols::SymbolsInput> {
+        match self {
+            Commands::PullQuotes { symbols, .. }
+            | Commands::PullOptionChain { symbols, .. }
+            | Commands::PublishOptionChain { symbols, .. }
+            | Commands::PerformAll { symbols, .. }
+            | Commands::WatchMode { symbols, .. }
+            | Commands::CalculateAtr { symbols, .. }
+            | Commands::CalculateRollingSharpe { symbols, .. }
+            | Commands::CalculateStrategySharpe { symbols, .. }
+            | Commands::CalculateVwap { symbols, .. }
+            | Commands::CalculateMaxDrop { symbols, .. }
+            | Commands::CalculateMomentum { symbols, .. }
+            | Commands::CalculateCompoundedReturn { symbols, .. }
+            | Commands::CalculateIndicators { symbols, .. }
+            | Commands::CalculateImpliedMove { symbols, .. }
+            | Commands::CalculateStreaks { symbols, .. }
+            | Commands::CalculateRecoveryFactor { symbols, .. }
+            | Commands::FilterSymbols { symbols, .. }
+            | Commands::ClearOptionChain { symbols, .. }
+            | Commands::Backtest { symbols, .. }
+            | Commands::GenerateSignals { symbols, .. }
+            | Commands::ExportSharpe { symbols, .. } => Some(symbols),
+            _ => None,
+        }
+    }
 }
 
 #[tokio::main]
@@ -63,9 +321,16 @@ enum Commands {
 async fn main() {
     dotenv().ok();
 
-    env_logger::init();
+    logging::init();
 
     let args = Args::parse();
+    let config = config::Config::from_env();
+
+    // Runs before the database is opened so a broken database is reported like the other checks.
+    if let Commands::TestConnection = args.command {
+        let passed = diagnostics::test_connection().await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
     let conn = store::sqlite::init_connection();
     if let Err(err) = conn {
@@ -74,51 +339,194 @@ async fn main() {
     }
     let mut conn = conn.unwrap();
 
+    // Kept alive until the command finishes so a temporary symbols file is not removed early.
+    let symbols_file = match args.command.symbols().map(|s| s.resolve()).transpose() {
+        Ok(symbols_file) => symbols_file,
+        Err(err) => {
+            log::error!("Error reading symbols: {}", err);
+            return;
+        }
+    };
+    let symbols_file_path = symbols_file
+        .as_ref()
+        .map(|f| f.path().to_string())
+        .unwrap_or_default();
+
     match args.command {
-        Commands::PullQuotes { symbols_file_path } => {
-            match quotes::pull_and_save(&symbols_file_path, &mut conn).await {
+        Commands::PullQuotes {
+            force,
+            period,
+            since,
+            ..
+        } => {
+            match quotes::pull_and_save(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+                force,
+                period,
+                since,
+            )
+            .await
+            {
                 Ok(_) => log::info!("Successfully pulled and saved quotes"),
                 Err(err) => log::error!("Error pulling and saving quotes: {}", err),
             }
-            match atr::calculate_and_save(&symbols_file_path, &mut conn) {
+            match atr::calculate_and_save(
+                &symbols_file_path,
+                &mut conn,
+                atr::AtrMode::Ratio,
+                &config,
+                false,
+                false,
+            ) {
                 Ok(_) => log::info!("Successfully calculated ATR and saved to DB"),
                 Err(err) => log::error!("Error calculating ATR: {}", err),
             }
         }
 
-        Commands::CalculateAtr { symbols_file_path } => {
-            match atr::calculate_and_save(&symbols_file_path, &mut conn) {
+        Commands::CalculateAtr {
+            mode,
+            no_plot,
+            halt_on_discontinuity,
+            ..
+        } => {
+            match atr::calculate_and_save(
+                &symbols_file_path,
+                &mut conn,
+                mode,
+                &config,
+                !no_plot,
+                halt_on_discontinuity,
+            ) {
                 Ok(_) => log::info!("Successfully calculated ATR and saved to DB"),
                 Err(err) => log::error!("Error calculating ATR: {}", err),
             }
         }
 
-        Commands::PullOptionChain { symbols_file_path } => {
-            match option::retrieve_option_chains_base_on_ranges(
+        Commands::CalculateRollingSharpe { window, .. } => {
+            let sharpe_config = model::SharpeConfig::builder()
+                .risk_free_rate(config.risk_free_rate)
+                .min_candles(window)
+                .build();
+            match sharpe::calculate_and_save_rolling(
                 &symbols_file_path,
-                &model::OptionChainSide::Put,
-                conn,
-            )
-            .await
-            {
-                Ok(_) => log::info!("Successfully pulled and saved option chains"),
-                Err(err) => log::error!("Error pulling option chains: {}", err),
+                &mut conn,
+                window,
+                &sharpe_config,
+            ) {
+                Ok(_) => log::info!("Successfully calculated rolling Sharpe and saved to DB"),
+                Err(err) => log::error!("Error calculating rolling Sharpe: {}", err),
             }
         }
 
-        Commands::PerformAll { symbols_file_path } => {
-            match quotes::pull_and_save(&symbols_file_path, &mut conn).await {
-                Ok(_) => log::info!("Successfully pulled and saved quotes"),
-                Err(err) => log::error!("Error pulling and saving quotes: {}", err),
+        Commands::CalculateVwap { window, .. } => {
+            match vwap::calculate_and_save(&symbols_file_path, &mut conn, window, &config) {
+                Ok(_) => log::info!("Successfully calculated VWAP and saved to DB"),
+                Err(err) => log::error!("Error calculating VWAP: {}", err),
             }
-            match atr::calculate_and_save(&symbols_file_path, &mut conn) {
-                Ok(_) => log::info!("Successfully calculated ATR and saved to DB"),
-                Err(err) => log::error!("Error calculating ATR: {}", err),
+        }
+
+        Commands::CalculateMaxDrop {
+            period,
+            rolling,
+            all_periods,
+            ..
+        } => {
+            let result = if all_periods {
+                maxdrop::calculate_all_periods(&symbols_file_path, &mut conn, &config)
+            } else {
+                maxdrop::calculate_and_save(&symbols_file_path, &mut conn, period, rolling, &config)
+            };
+            match result {
+                Ok(_) => log::info!("Successfully calculated max drops and saved to DB"),
+                Err(err) => log::error!("Error calculating max drops: {}", err),
             }
+        }
+
+        Commands::CalculateMomentum { period, .. } => {
+            match momentum::calculate_and_save(&symbols_file_path, &mut conn, period, &config) {
+                Ok(_) => log::info!("Successfully calculated momentum and saved to DB"),
+                Err(err) => log::error!("Error calculating momentum: {}", err),
+            }
+        }
+
+        Commands::CalculateCompoundedReturn { .. } => {
+            match cagr::calculate_and_save(&symbols_file_path, &mut conn, &config) {
+                Ok(_) => log::info!("Successfully calculated CAGR and saved to DB"),
+                Err(err) => log::error!("Error calculating CAGR: {}", err),
+            }
+        }
+
+        Commands::CalculateIndicators {
+            indicators: names,
+            period,
+            ..
+        } => match indicators::calculate_and_save(
+            &symbols_file_path,
+            &mut conn,
+            &names,
+            period,
+            &config,
+        ) {
+            Ok(_) => log::info!("Successfully calculated indicators and saved to DB"),
+            Err(err) => log::error!("Error calculating indicators: {}", err),
+        },
+
+        Commands::CalculateImpliedMove {
+            warn_high_iv_move, ..
+        } => match straddle::calculate_and_save(&symbols_file_path, &mut conn, warn_high_iv_move) {
+            Ok(_) => log::info!("Successfully estimated implied moves and saved to DB"),
+            Err(err) => log::error!("Error estimating implied moves: {}", err),
+        },
+
+        Commands::CalculateStrategySharpe { .. } => {
+            match sharpe::print_strategy_sharpes(&symbols_file_path, &conn, &config) {
+                Ok(_) => log::info!("Successfully calculated strategy Sharpe ratios"),
+                Err(err) => log::error!("Error calculating strategy Sharpe ratios: {}", err),
+            }
+        }
+
+        Commands::CalculateStreaks {
+            warn_dd_duration_days,
+            ..
+        } => {
+            match streaks::calculate_and_save(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+                warn_dd_duration_days,
+            ) {
+                Ok(_) => log::info!("Successfully calculated drawdown durations and saved to DB"),
+                Err(err) => log::error!("Error calculating drawdown durations: {}", err),
+            }
+        }
+
+        Commands::CalculateRecoveryFactor { .. } => {
+            match streaks::calculate_and_save_recovery_factor(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+            ) {
+                Ok(_) => log::info!("Successfully calculated recovery factors and saved to DB"),
+                Err(err) => log::error!("Error calculating recovery factors: {}", err),
+            }
+        }
+
+        Commands::PullOptionChain {
+            side,
+            alerts,
+            filter,
+            ..
+        } => {
             match option::retrieve_option_chains_base_on_ranges(
                 &symbols_file_path,
-                &model::OptionChainSide::Put,
-                conn,
+                &side,
+                &mut conn,
+                &config,
+                &alerts,
+                &filter,
+                true,
             )
             .await
             {
@@ -127,8 +535,294 @@ async fn main() {
             }
         }
 
-        Commands::PublishOptionChain { symbols_file_path } => {
-            match option::publish_option_chains(&symbols_file_path, conn).await {
+        Commands::PerformAll {
+            alerts,
+            filter,
+            metrics,
+            force,
+            steps,
+            ..
+        } => {
+            let steps = match steps.resolve() {
+                Ok(steps) => steps,
+                Err(err) => {
+                    log::error!("Error running pipeline: {}", err);
+                    return;
+                }
+            };
+            if metrics {
+                tokio::spawn(async {
+                    if let Err(err) = metrics::serve().await {
+                        log::error!("Error serving metrics: {}", err);
+                    }
+                });
+            }
+            pipeline::run_pipeline(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+                &alerts,
+                &filter,
+                &steps,
+                force,
+            )
+            .await;
+        }
+
+        Commands::WatchMode {
+            schedule,
+            alerts,
+            filter,
+            steps,
+            ..
+        } => {
+            let steps = match steps.resolve() {
+                Ok(steps) => steps,
+                Err(err) => {
+                    log::error!("Error running pipeline: {}", err);
+                    return;
+                }
+            };
+            pipeline::watch(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+                &alerts,
+                &filter,
+                &steps,
+                &schedule,
+            )
+            .await;
+        }
+
+        Commands::FilterSymbols {
+            output_file_path,
+            min_candles,
+            min_sharpe,
+            ..
+        } => match symbols::filter_symbols(
+            &symbols_file_path,
+            &output_file_path,
+            min_candles,
+            min_sharpe,
+            &conn,
+        ) {
+            Ok(_) => log::info!("Successfully filtered symbols"),
+            Err(err) => log::error!("Error filtering symbols: {}", err),
+        },
+
+        Commands::ListSymbols {
+            symbols_file_path,
+            from_db,
+        } => match symbols::list_symbols(symbols_file_path.as_deref(), from_db, &conn) {
+            Ok(_) => log::info!("Successfully listed symbols"),
+            Err(err) => log::error!("Error listing symbols: {}", err),
+        },
+
+        Commands::ClearOptionChain { before, .. } => {
+            match option::clear_option_chains(&symbols_file_path, before.as_deref(), &mut conn) {
+                Ok(_) => log::info!("Successfully cleared option chains"),
+                Err(err) => log::error!("Error clearing option chains: {}", err),
+            }
+        }
+
+        Commands::PrintConfig => println!("{:#?}", config),
+
+        Commands::ImportCandles { csv_path, symbol } => {
+            match quotes::import_from_csv(&csv_path, symbol.as_deref(), &mut conn).await {
+                Ok(_) => log::info!("Successfully imported candles"),
+                Err(err) => log::error!("Error importing candles: {}", err),
+            }
+        }
+
+        Commands::ClearTable { table_name, vacuum } => {
+            match store::sqlite::clear_table(&conn, &table_name) {
+                Ok(rows) => log::info!("Successfully cleared {} rows from {}", rows, table_name),
+                Err(err) => {
+                    log::error!("Error clearing table: {}", err);
+                    return;
+                }
+            }
+            if vacuum {
+                match store::sqlite::vacuum(&conn) {
+                    Ok(_) => log::info!("Successfully vacuumed database"),
+                    Err(err) => log::error!("Error vacuuming database: {}", err),
+                }
+            }
+        }
+
+        Commands::ExportDatabase {
+            output_dir,
+            compress,
+        } => match export::export_database(&conn, &output_dir, compress) {
+            Ok(rows) => log::info!("Successfully exported {} rows", rows),
+            Err(err) => log::error!("Error exporting database: {}", err),
+        },
+
+        Commands::Checkpoint { mode } => match store::sqlite::run_checkpoint(&conn, &mode) {
+            Ok(_) => log::info!("Successfully checkpointed database"),
+            Err(err) => log::error!("Error checkpointing database: {}", err),
+        },
+
+        Commands::ExportSharpe { output_path, .. } => {
+            match export::export_sharpe(&symbols_file_path, &conn, &output_path, &config) {
+                Ok(rows) => println!("Wrote {} rows to {}", rows, output_path),
+                Err(err) => log::error!("Error exporting Sharpe ratios: {}", err),
+            }
+        }
+
+        Commands::TestConnection => unreachable!("handled before opening the database"),
+
+        Commands::CheckDatabase => match store::sqlite::check_integrity(&conn) {
+            Ok(warnings) if warnings.is_empty() => log::info!("Database check passed"),
+            Ok(warnings) => {
+                for warning in &warnings {
+                    log::error!("{}", warning);
+                }
+                std::process::exit(1);
+            }
+            Err(err) => {
+                log::error!("Error checking database: {}", err);
+                std::process::exit(1);
+            }
+        },
+
+        Commands::DatabaseStats { candles } => match store::sqlite::get_database_stats(&conn) {
+            Ok(stats) => {
+                println!("{:<24} {:>12} {:>14}", "TABLE", "ROWS", "SIZE_BYTES");
+                for table in &stats.tables {
+                    println!(
+                        "{:<24} {:>12} {:>14}",
+                        table.name, table.row_count, table.size_bytes
+                    );
+                }
+                let total = stats.total_size_bytes();
+                println!("{:<24} {:>12} {:>14}", "TOTAL", "", total);
+                if total > config.db_warn_size_mb * 1024 * 1024 {
+                    log::warn!(
+                        size_bytes = total, threshold_mb = config.db_warn_size_mb;
+                        "Database is larger than the warning threshold"
+                    );
+                }
+                if candles {
+                    let summaries = store::candle::get_all_symbols(&conn).and_then(|symbols| {
+                        symbols
+                            .iter()
+                            .map(|symbol| store::candle::get_candle_summary(&conn, symbol))
+                            .collect::<rusqlite::Result<Vec<_>>>()
+                    });
+                    match summaries {
+                        Ok(summaries) => {
+                            let date = |ts: u32| {
+                                chrono::DateTime::from_timestamp(ts as i64, 0)
+                                    .unwrap_or_default()
+                                    .format("%Y-%m-%d")
+                            };
+                            println!(
+                                "\n{:<10} {:>8} {:<10} {:<10} {:>10} {:>10} {:>10}",
+                                "SYMBOL",
+                                "CANDLES",
+                                "OLDEST",
+                                "NEWEST",
+                                "AVG_CLOSE",
+                                "MIN_CLOSE",
+                                "MAX_CLOSE"
+                            );
+                            for s in &summaries {
+                                println!(
+                                    "{:<10} {:>8} {:<10} {:<10} {:>10.2} {:>10.2} {:>10.2}",
+                                    s.symbol,
+                                    s.count,
+                                    date(s.oldest_ts),
+                                    date(s.newest_ts),
+                                    s.avg_close,
+                                    s.min_close,
+                                    s.max_close
+                                );
+                            }
+                        }
+                        Err(err) => log::error!("Error reading candle summaries: {}", err),
+                    }
+                }
+            }
+            Err(err) => log::error!("Error reading database stats: {}", err),
+        },
+
+        Commands::ListDropbox { folder_path } => match dropbox::list_files(&folder_path).await {
+            Ok(entries) => {
+                println!("{:<40} {:>12} {:<20} PATH", "NAME", "SIZE", "MODIFIED");
+                for entry in entries {
+                    println!(
+                        "{:<40} {:>12} {:<20} {}",
+                        entry.name, entry.size, entry.modified, entry.path
+                    );
+                }
+            }
+            Err(err) => log::error!("Error listing Dropbox folder: {}", err),
+        },
+
+        Commands::Backtest {
+            target_delta, dte, ..
+        } => match backtest::print_backtests(&symbols_file_path, &conn, target_delta, dte) {
+            Ok(_) => log::info!("Successfully ran backtest"),
+            Err(err) => log::error!("Error running backtest: {}", err),
+        },
+
+        Commands::GenerateSignals { .. } => {
+            match signal::print_signals(&symbols_file_path, &conn, &config) {
+                Ok(_) => log::info!("Successfully generated signals"),
+                Err(err) => log::error!("Error generating signals: {}", err),
+            }
+        }
+
+        Commands::PortfolioSummary {
+            positions_file,
+            format,
+        } => match portfolio::print_summary(&positions_file, &mut conn, format, &config) {
+            Ok(_) => log::info!("Successfully summarised portfolio"),
+            Err(err) => log::error!("Error summarising portfolio: {}", err),
+        },
+
+        Commands::DeleteDropboxFile { path } => match dropbox::delete_file(&path).await {
+            Ok(_) => log::info!("Successfully deleted {} from Dropbox", path),
+            Err(err) => log::error!("Error deleting from Dropbox: {}", err),
+        },
+
+        Commands::RetrieveOptionChain {
+            symbol,
+            timestamp,
+            run_id,
+            filter,
+        } => {
+            match option::print_option_chain(
+                &symbol,
+                timestamp.as_deref(),
+                run_id.as_deref(),
+                &filter,
+                &mut conn,
+            ) {
+                Ok(_) => log::info!("Successfully retrieved option chain"),
+                Err(err) => log::error!("Error retrieving option chain: {}", err),
+            }
+        }
+
+        Commands::PublishOptionChain {
+            alerts,
+            sort_by,
+            best_per_symbol,
+            min_rate_of_return,
+            ..
+        } => {
+            match option::publish_option_chains(
+                &symbols_file_path,
+                &mut conn,
+                &config,
+                &alerts,
+                sort_by,
+                best_per_symbol.then_some(min_rate_of_return),
+            )
+            .await
+            {
                 Ok(_) => log::info!("Successfully published option chains"),
                 Err(err) => log::error!("Error publishing option chains: {}", err),
             }
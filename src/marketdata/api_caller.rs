@@ -1,4 +1,4 @@
-use super::super::model;
+use super::super::{black_scholes, constants, model};
 use super::response;
 use crate::http::client::{self, RequestError};
 use chrono::{DateTime, Local, TimeZone};
@@ -7,6 +7,11 @@ use std::{collections::HashMap, env};
 // Base URL for the market data API.
 const BASE_URL: &str = "https://api.marketdata.app/";
 
+// Base URL of the market data API, overridden by MARKETDATA_BASE_URL, as for a mock server.
+fn base_url() -> String {
+    env::var("MARKETDATA_BASE_URL").unwrap_or_else(|_| BASE_URL.to_string())
+}
+
 // Checks the status returned from the API and returns an error if the status is not "ok".
 fn check_status(s: &str, err: Option<String>) -> Result<(), RequestError> {
     match s {
@@ -25,7 +30,7 @@ pub async fn market_status() -> Result<model::MarketStatus, RequestError> {
 
     let resp = client::request::<response::MarketStatus>(
         client::Method::Get,
-        format!("{}v1/markets/status/", BASE_URL).as_str(),
+        format!("{}v1/markets/status/", base_url()).as_str(),
         HashMap::new(),
         HashMap::new(),
         Some(token.as_str()),
@@ -43,17 +48,27 @@ pub async fn market_status() -> Result<model::MarketStatus, RequestError> {
     }
 }
 
-/// Fetches daily candle data for a given stock symbol.
-pub async fn stock_candle(
+/// Fetches weekly candle data for a given stock symbol.
+pub async fn weekly_candle(
     symbol: &str,         // Stock symbol.
     to: &DateTime<Local>, // End timestamp.
     count: u32,           // Number of candles to fetch.
+) -> Result<Vec<model::Candle>, RequestError> {
+    stock_candle(symbol, to, count, model::Period::Weekly).await
+}
+
+/// Fetches candle data for a given stock symbol.
+pub async fn stock_candle(
+    symbol: &str,          // Stock symbol.
+    to: &DateTime<Local>,  // End timestamp.
+    count: u32,            // Number of candles to fetch.
+    period: model::Period, // Candle resolution.
 ) -> Result<Vec<model::Candle>, RequestError> {
     let token = env::var("marketdata_token").map_err(|_| RequestError::TokenNotSet)?;
 
     let resp = client::request::<response::DailyCandles>(
         client::Method::Get,
-        format!("{}v1/stocks/candles/daily/{}", BASE_URL, symbol).as_str(),
+        format!("{}v1/stocks/candles/{}/{}", base_url(), period, symbol).as_str(),
         HashMap::from([
             ("to", format!("{}", to.timestamp()).as_str()),
             ("countback", &count.to_string()),
@@ -88,7 +103,7 @@ pub async fn bulk_candles(
 
     let resp = client::request::<response::BulkCandles>(
         client::Method::Get,
-        format!("{}v1/stocks/bulkcandles/daily/", BASE_URL).as_str(),
+        format!("{}v1/stocks/bulkcandles/daily/", base_url()).as_str(),
         HashMap::from([("symbols", symbols.join(",").as_str())]),
         HashMap::new(),
         Some(&token),
@@ -131,7 +146,7 @@ pub async fn option_chain(
     .join("-");
     let resp = client::request::<response::OptionChain>(
         client::Method::Get,
-        &format!("{}v1/options/chain/{}/", BASE_URL, symbol),
+        &format!("{}v1/options/chain/{}/", base_url(), symbol),
         HashMap::from([
             ("strike", strike_str.as_str()),
             (
@@ -166,15 +181,32 @@ pub async fn option_chain(
             _ => return Err(RequestError::Other("Invalid timestamp".into())),
         };
         let updated_date_str = updated_datetime.format("%Y-%m-%d").to_string();
-        candles.push(model::OptionStrikeCandle {
+        let mid = calculate_mid_price(resp.bid[i], resp.ask[i], resp.last[i]);
+        if mid > 0.0 && ((resp.mid[i] - mid) / mid).abs() > constants::MID_PRICE_TOLERANCE {
+            log::warn!(
+                symbol = symbol, strike = resp.strike[i], api_mid = resp.mid[i], mid = mid;
+                "API mid price deviates from the bid/ask mid"
+            );
+        }
+        let side = match resp.side[i].as_str() {
+            "call" => model::OptionChainSide::Call,
+            "put" => model::OptionChainSide::Put,
+            _ => return Err(RequestError::Other("Unknown side".into())),
+        };
+        let iv = implied_volatility(
+            resp.iv.get(i).copied().flatten(),
+            &side,
+            resp.mid[i],
+            resp.underlying_price[i],
+            resp.strike[i],
+            resp.dte[i],
+        );
+        let mut candle = model::OptionStrikeCandle {
             underlying: resp.underlying[i].clone(),
             strike: resp.strike[i],
             underlying_price: resp.underlying_price[i],
-            side: match resp.side[i].as_str() {
-                "call" => model::OptionChainSide::Call,
-                "put" => model::OptionChainSide::Put,
-                _ => return Err(RequestError::Other("Unknown side".into())),
-            },
+            vwap: 0.0,
+            side,
             bid: resp.bid[i],
             mid: resp.mid[i],
             ask: resp.ask[i],
@@ -192,18 +224,88 @@ pub async fn option_chain(
             )
             .parse()
             .unwrap(),
+            liquidity_score: 0.0,
             strike_from: format!("{:.3}", strike_range.0).parse().unwrap(),
             strike_to: format!("{:.3}", strike_range.1).parse().unwrap(),
-        });
+            implied_volatility: iv,
+            delta: resp.delta.get(i).copied().flatten().unwrap_or(0.0),
+            theta: resp.theta.get(i).copied().flatten().unwrap_or(0.0),
+            vega: resp.vega.get(i).copied().flatten().unwrap_or(0.0),
+            iv_hv_ratio: 0.0,
+            implied_move_pct: 0.0,
+            rolling_sharpe_252: 0.0,
+            atr_trend: 0.0,
+            roc: 0.0,
+            max_dd_duration_days: 0,
+            recovery_factor: 0.0,
+            signal: String::new(),
+            earnings_risk: false,
+            cagr: 0.0,
+        };
+        candle.liquidity_score = format!("{:.3}", model::option_liquidity_score(&candle))
+            .parse()
+            .unwrap();
+        candles.push(candle);
     }
     Ok(candles)
 }
 
-// Calculates the number of weeks given the days to expiration.
+// Mid price of a quote: halfway between the bid and ask, or the last traded price when either
+// side is missing.
+fn calculate_mid_price(bid: f64, ask: f64, last: f64) -> f64 {
+    if bid > 0.0 && ask > 0.0 {
+        (bid + ask) / 2.0
+    } else {
+        last
+    }
+}
+
+// Implied volatility of a quote as the API returns it, or solved from the mid price with
+// Black-Scholes when the API has none. 0 when neither is available.
+fn implied_volatility(
+    api_iv: Option<f64>,
+    side: &model::OptionChainSide,
+    mid: f64,
+    underlying_price: f64,
+    strike: f64,
+    dte: u32,
+) -> f64 {
+    match api_iv {
+        Some(iv) if iv > 0.0 => iv,
+        _ => black_scholes::implied_volatility(
+            side,
+            mid,
+            underlying_price,
+            strike,
+            dte as f64 / 365.0,
+            constants::DEFAULT_RISK_FREE_RATE,
+        )
+        .unwrap_or(0.0),
+    }
+}
+
+// Calculates the number of weeks given the days to expiration. Each leftover day counts as a
+// fifth of a trading week, and a same-day expiry counts as one day so the rate of return stays
+// finite.
 fn num_of_weeks(dte: u32) -> f64 {
-    if (5..=7).contains(&dte) {
+    if dte == 0 {
+        1.0 / 5.0
+    } else if (5..=7).contains(&dte) {
         1.0
     } else {
         (dte / 7) as f64 + (dte % 7) as f64 / 5.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn num_of_weeks_counts_trading_weeks() {
+        assert_eq!(num_of_weeks(0), 0.2);
+        assert_eq!(num_of_weeks(5), 1.0);
+        assert_eq!(num_of_weeks(7), 1.0);
+        assert_eq!(num_of_weeks(14), 2.0);
+    }
+}
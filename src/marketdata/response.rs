@@ -57,5 +57,13 @@ pub struct OptionChain {
     pub open_interest: Vec<u32>,    // Open interests.
     pub volume: Vec<u32>,           // Volumes.
     pub underlying_price: Vec<f64>, // Underlying prices.
+    #[serde(default)]
+    pub iv: Vec<Option<f64>>, // Implied volatilities.
+    #[serde(default)]
+    pub delta: Vec<Option<f64>>, // Deltas.
+    #[serde(default)]
+    pub theta: Vec<Option<f64>>, // Thetas.
+    #[serde(default)]
+    pub vega: Vec<Option<f64>>, // Vegas.
     pub errmsg: Option<String>,     // Error message (if any).
 }
@@ -0,0 +1,255 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    statistics,
+    store::{candle, max_drop},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Calculates the percentile and EMA of each symbol's max drops over non-overlapping windows
+/// of `period` candles and saves them to `max_drop`. With `rolling`, the same is done over
+/// every overlapping window and saved to `max_drop_rolling`, and a warning is logged when the
+/// two percentiles differ by more than `MAX_DROP_DISCREPANCY`. Symbols with fewer than
+/// `period * MAX_DROP_EMA_PERIOD` candles are skipped before their candles are read. Symbols
+/// that fail are skipped, and the run only fails, with a `PartialFailure`, when every symbol did.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    period: usize,           // Number of candles in each window.
+    rolling: bool,
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    max_drop::create_table_named(conn, "max_drop")?;
+    if rolling {
+        max_drop::create_table_named(conn, "max_drop_rolling")?;
+    }
+
+    let mut max_drops = Vec::with_capacity(symbols.len());
+    let mut rolling_max_drops = Vec::with_capacity(symbols.len());
+    // Enough candles for the EMA of the non-overlapping windows.
+    let min_candles = period as u32 * constants::MAX_DROP_EMA_PERIOD;
+    let mut summary = model::RunSummary::default();
+    for symbol in symbols {
+        let count = candle::count_candles(conn, &symbol).with_symbol(&symbol)?;
+        if count < min_candles {
+            log::warn!(
+                symbol = symbol.as_str(), count = count, min_candles = min_candles;
+                "Not enough candles, skipping max drop"
+            );
+            summary.failure(&symbol, format!("{} of {} candles", count, min_candles));
+            continue;
+        }
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let Some(last_candle) = candles.last() else {
+            log::warn!(symbol = symbol.as_str(); "No candles, skipping max drop");
+            summary.failure(&symbol, "no candles");
+            continue;
+        };
+
+        let drops = calculate_max_drop(&candles, period);
+        let max_drop = match summarise(&symbol, period, &drops, last_candle.timestamp, config) {
+            Ok(max_drop) => max_drop,
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping max drop");
+                summary.failure(&symbol, err);
+                continue;
+            }
+        };
+
+        // The non-rolling max drop is kept even when the rolling one fails.
+        let percentile_drop = max_drop.percentile_drop;
+        max_drops.push(max_drop);
+        if !rolling {
+            summary.success(&symbol);
+            continue;
+        }
+
+        let drops = calculate_max_drop_rolling(&candles, period);
+        let rolling_max_drop =
+            match summarise(&symbol, period, &drops, last_candle.timestamp, config) {
+                Ok(rolling_max_drop) => rolling_max_drop,
+                Err(err) => {
+                    log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping rolling max drop");
+                    summary.failure(&symbol, err);
+                    continue;
+                }
+            };
+        let discrepancy =
+            (rolling_max_drop.percentile_drop - percentile_drop).abs() / percentile_drop;
+        if percentile_drop > 0.0 && discrepancy > constants::MAX_DROP_DISCREPANCY {
+            log::warn!(
+                symbol = symbol.as_str(),
+                max_drop = percentile_drop,
+                rolling_max_drop = rolling_max_drop.percentile_drop;
+                "Rolling and non-rolling max drops differ by {:.0}%", discrepancy * 100.0
+            );
+        }
+        rolling_max_drops.push(rolling_max_drop);
+        summary.success(&symbol);
+    }
+
+    max_drop::save_max_drops_to(conn, "max_drop", &max_drops)?;
+    if rolling {
+        max_drop::save_max_drops_to(conn, "max_drop_rolling", &rolling_max_drops)?;
+    }
+    summary.finish()
+}
+
+/// Calculates the max drops of every symbol for each of `MAX_DROP_ALL_PERIODS` and saves them
+/// to `max_drop`, reading each symbol's candles once. The computed values are logged at the end.
+pub fn calculate_all_periods(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    max_drop::create_table_named(conn, "max_drop")?;
+
+    let mut max_drops = Vec::with_capacity(symbols.len() * constants::MAX_DROP_ALL_PERIODS.len());
+    for symbol in symbols {
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let Some(last_candle) = candles.last() else {
+            log::warn!(symbol = symbol.as_str(); "No candles, skipping max drop");
+            continue;
+        };
+
+        for period in constants::MAX_DROP_ALL_PERIODS {
+            let drops = calculate_max_drop(&candles, period);
+            match summarise(&symbol, period, &drops, last_candle.timestamp, config) {
+                Ok(max_drop) => max_drops.push(max_drop),
+                Err(err) => {
+                    log::warn!(symbol = symbol.as_str(), period = period, err:% = err; "Skipping max drop")
+                }
+            }
+        }
+    }
+
+    max_drop::save_max_drops_to(conn, "max_drop", &max_drops)?;
+
+    for max_drop in &max_drops {
+        log::info!(
+            symbol = max_drop.symbol.as_str(),
+            period = max_drop.period,
+            percentile_drop = max_drop.percentile_drop,
+            ema_drop = max_drop.ema_drop;
+            "Max drop"
+        );
+    }
+    Ok(())
+}
+
+// Reduces a series of max drops to its percentile and EMA.
+fn summarise(
+    symbol: &str,
+    period: usize,
+    drops: &[f64],
+    timestamp: u32,
+    config: &Config,
+) -> model::Result<model::MaxDrop> {
+    Ok(model::MaxDrop {
+        symbol: symbol.to_string(),
+        period: period as u32,
+        percentile_drop: statistics::percentile(drops, config.percentile)?,
+        ema_drop: statistics::exponential_moving_average(drops, constants::MAX_DROP_EMA_PERIOD)?,
+        timestamp,
+    })
+}
+
+/// Max drop of each non-overlapping window of `period` candles. A trailing partial window is
+/// ignored.
+pub fn calculate_max_drop(candles: &[model::Candle], period: usize) -> Vec<f64> {
+    if period == 0 {
+        return Vec::new();
+    }
+    candles.chunks_exact(period).map(max_drop).collect()
+}
+
+/// Max drop of every overlapping window of `period` candles.
+pub fn calculate_max_drop_rolling(candles: &[model::Candle], period: usize) -> Vec<f64> {
+    if period == 0 {
+        return Vec::new();
+    }
+    candles.windows(period).map(max_drop).collect()
+}
+
+// Largest fall from the window's opening price to its lowest low, as a fraction of the open.
+fn max_drop(window: &[model::Candle]) -> f64 {
+    let open = window[0].open;
+    let low = window.iter().map(|c| c.low).fold(f64::INFINITY, f64::min);
+    ((open - low) / open).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+    use std::io::Write;
+
+    fn candle(open: f64, low: f64) -> model::Candle {
+        model::Candle {
+            symbol: "TEST".to_string(),
+            open,
+            high: open,
+            low,
+            close: open,
+            volume: 100,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn max_drop_is_the_fall_from_the_open_to_the_lowest_low() {
+        let candles = [candle(100.0, 95.0), candle(98.0, 80.0), candle(90.0, 85.0)];
+        assert!((calculate_max_drop(&candles, 3)[0] - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn max_drop_ignores_a_trailing_partial_window() {
+        let candles = [
+            candle(100.0, 90.0),
+            candle(100.0, 90.0),
+            candle(100.0, 50.0),
+        ];
+        assert_eq!(calculate_max_drop(&candles, 2).len(), 1);
+        assert_eq!(calculate_max_drop_rolling(&candles, 2).len(), 2);
+        assert!(calculate_max_drop(&candles, 0).is_empty());
+    }
+
+    #[tokio::test]
+    async fn symbols_with_too_few_candles_are_skipped() {
+        let mut conn = sqlite::test_conn();
+        candle::create_table(&conn).unwrap();
+        let candles: Vec<model::Candle> = (1..=8)
+            .map(|day| model::Candle {
+                timestamp: day * 86_400,
+                ..candle(100.0, 90.0)
+            })
+            .chain((1..=7).map(|day| model::Candle {
+                symbol: "SHORT".to_string(),
+                timestamp: day * 86_400,
+                ..candle(100.0, 90.0)
+            }))
+            .collect();
+        candle::save_candles(&mut conn, &candles).await.unwrap();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "TEST\nSHORT").unwrap();
+        let symbols_path = file.path().to_str().unwrap();
+        let config = Config::from_env();
+
+        calculate_and_save(symbols_path, &mut conn, 2, false, &config).unwrap();
+        assert_eq!(max_drop::get_all_symbols(&conn).unwrap(), vec!["TEST"]);
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "SHORT").unwrap();
+        let result =
+            calculate_and_save(file.path().to_str().unwrap(), &mut conn, 2, false, &config);
+        assert!(matches!(result, Err(model::QuotesError::PartialFailure(_))));
+    }
+}
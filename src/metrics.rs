@@ -0,0 +1,108 @@
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use lazy_static::lazy_static;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+use rusqlite::Connection;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::store;
+
+const DEFAULT_METRICS_PORT: u16 = 9090;
+
+// Set once the metrics server is listening, so that gauges that are costly to compute are only
+// updated when they can be scraped.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    pub static ref API_CALLS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "api_calls_total",
+        "Number of HTTP API calls made.",
+        &["module", "status"]
+    )
+    .unwrap();
+    pub static ref API_CALL_DURATION_SECONDS: HistogramVec = register_histogram_vec!(
+        "api_call_duration_seconds",
+        "Duration of HTTP API calls in seconds.",
+        &["module"]
+    )
+    .unwrap();
+    pub static ref SYMBOLS_PROCESSED: IntGauge = register_int_gauge!(
+        "symbols_processed",
+        "Number of symbols processed in this run."
+    )
+    .unwrap();
+    pub static ref DB_CANDLE_COUNT: IntGauge = register_int_gauge!(
+        "db_candle_count",
+        "Number of candles stored in the database."
+    )
+    .unwrap();
+}
+
+/// Returns whether the metrics server is running.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets `db_candle_count` to the number of candles of every period in the database, when the
+/// metrics server is running. A failed count is logged rather than failing the run.
+pub fn update_db_candle_count(conn: &Connection) {
+    if !enabled() {
+        return;
+    }
+    match store::candle::count_all_candles(conn) {
+        Ok(count) => DB_CANDLE_COUNT.set(count),
+        Err(err) => log::warn!(err:% = err; "Could not count the stored candles"),
+    }
+}
+
+/// Serves the `/metrics` path on `METRICS_PORT` (default 9090) until the process exits.
+pub async fn serve() -> std::io::Result<()> {
+    let port = env::var("METRICS_PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_METRICS_PORT);
+    let listener = TcpListener::bind(("0.0.0.0", port)).await?;
+    ENABLED.store(true, Ordering::Relaxed);
+    log::info!("Serving metrics on port {}", port);
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let len = match stream.read(&mut buf).await {
+                Ok(len) => len,
+                Err(err) => {
+                    log::warn!("Failed to read metrics request: {}", err);
+                    return;
+                }
+            };
+            let request = String::from_utf8_lossy(&buf[..len]);
+            let response = if request.starts_with("GET /metrics ") {
+                let mut body = Vec::new();
+                TextEncoder::new()
+                    .encode(&prometheus::gather(), &mut body)
+                    .unwrap();
+                format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                    TextEncoder::new().format_type(),
+                    body.len(),
+                    String::from_utf8_lossy(&body)
+                )
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            };
+            if let Err(err) = stream.write_all(response.as_bytes()).await {
+                log::warn!("Failed to write metrics response: {}", err);
+            }
+        });
+    }
+}
@@ -3,6 +3,7 @@ use std::{
     error::Error,
     fmt::Display,
     io::{self, BufWriter},
+    str::FromStr,
 };
 
 use csv::Writer;
@@ -10,21 +11,61 @@ use rusqlite::{
     types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
     ToSql,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use telegram_bot_api::bot::APIResponseError;
 
-use crate::http::client;
+use crate::{constants, http::client};
 
 /// Represents the market status.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum MarketStatus {
     Open,
     Closed,
     Null,
 }
 
+/// Resolution of the candles pulled from the API.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Default)]
+pub enum Period {
+    #[default]
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Display for Period {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The resolution as the candles endpoint expects it.
+        match self {
+            Period::Daily => write!(f, "daily"),
+            Period::Weekly => write!(f, "weekly"),
+            Period::Monthly => write!(f, "monthly"),
+        }
+    }
+}
+
+/// A rule-based trading signal for selling puts on an underlying.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Signal {
+    Buy, // Not produced yet; the rule set only covers selling puts.
+    Sell,
+    Hold,
+    Warning(String),
+}
+
+impl Display for Signal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Signal::Buy => write!(f, "buy"),
+            Signal::Sell => write!(f, "sell"),
+            Signal::Hold => write!(f, "hold"),
+            Signal::Warning(reason) => write!(f, "warning: {}", reason),
+        }
+    }
+}
+
 /// Structure representing a candle (OHLCV data).
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Candle {
     pub symbol: String, // Symbol of the asset.
     pub open: f64,      // Opening price.
@@ -35,7 +76,96 @@ pub struct Candle {
     pub timestamp: u32, // Timestamp of the candle.
 }
 
-#[derive(Debug)]
+impl Candle {
+    /// Typical price `(high + low + close) / 3`.
+    ///
+    /// ```
+    /// # use market_int::model::Candle;
+    /// let candle = Candle {
+    ///     symbol: "AAPL".to_string(),
+    ///     open: 9.0,
+    ///     high: 12.0,
+    ///     low: 8.0,
+    ///     close: 10.0,
+    ///     volume: 100,
+    ///     timestamp: 0,
+    /// };
+    /// assert_eq!(candle.typical_price(), 10.0);
+    /// ```
+    pub fn typical_price(&self) -> f64 {
+        (self.high + self.low + self.close) / 3.0
+    }
+
+    /// Median price `(high + low) / 2`.
+    ///
+    /// ```
+    /// # use market_int::model::Candle;
+    /// let candle = Candle {
+    ///     symbol: "AAPL".to_string(),
+    ///     open: 9.0,
+    ///     high: 12.0,
+    ///     low: 8.0,
+    ///     close: 10.0,
+    ///     volume: 100,
+    ///     timestamp: 0,
+    /// };
+    /// assert_eq!(candle.hl2(), 10.0);
+    /// ```
+    pub fn hl2(&self) -> f64 {
+        (self.high + self.low) / 2.0
+    }
+
+    /// Average price `(open + high + low + close) / 4`.
+    ///
+    /// ```
+    /// # use market_int::model::Candle;
+    /// let candle = Candle {
+    ///     symbol: "AAPL".to_string(),
+    ///     open: 9.0,
+    ///     high: 12.0,
+    ///     low: 8.0,
+    ///     close: 10.0,
+    ///     volume: 100,
+    ///     timestamp: 0,
+    /// };
+    /// assert_eq!(candle.ohlc4(), 9.75);
+    /// ```
+    pub fn ohlc4(&self) -> f64 {
+        (self.open + self.high + self.low + self.close) / 4.0
+    }
+    /// Aggregates a week of daily candles, sorted from oldest to newest, into one weekly candle.
+    /// Returns None for an empty slice.
+    pub fn from_weekly(candles: &[Candle]) -> Option<Candle> {
+        Self::aggregate(candles)
+    }
+
+    /// Aggregates a month of daily candles, sorted from oldest to newest, into one monthly
+    /// candle. Returns None for an empty slice.
+    pub fn from_monthly(candles: &[Candle]) -> Option<Candle> {
+        Self::aggregate(candles)
+    }
+
+    // Opens at the first candle, closes at the last, spans the extreme high and low and sums
+    // the volume. Timestamped with the first candle.
+    fn aggregate(candles: &[Candle]) -> Option<Candle> {
+        let first = candles.first()?;
+        let last = candles.last()?;
+        Some(Candle {
+            symbol: first.symbol.clone(),
+            open: first.open,
+            high: candles
+                .iter()
+                .map(|c| c.high)
+                .fold(f64::NEG_INFINITY, f64::max),
+            low: candles.iter().map(|c| c.low).fold(f64::INFINITY, f64::min),
+            close: last.close,
+            volume: candles.iter().map(|c| c.volume).sum(),
+            timestamp: first.timestamp,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TrueRange {
     pub symbol: String, // Symbol of the asset.
     pub percentile_range: f64,
@@ -43,22 +173,97 @@ pub struct TrueRange {
     pub timestamp: u32,
 }
 
+/// Summary of a symbol's max drops over windows of `period` candles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxDrop {
+    pub symbol: String,       // Symbol of the asset.
+    pub period: u32,          // Number of candles in each window.
+    pub percentile_drop: f64, // Configured percentile of the max drops.
+    pub ema_drop: f64,        // EMA of the max drops.
+    pub timestamp: u32,       // Timestamp of the latest candle.
+}
+
+/// Settings of the Sharpe ratio calculation.
+#[derive(Debug, Clone, Default)]
+pub struct SharpeConfig {
+    pub risk_free_rate: Option<f64>, // Annual risk-free rate, the default rate when None.
+    pub min_candles: usize,          // Symbols with fewer candles are skipped.
+}
+
+impl SharpeConfig {
+    pub fn builder() -> SharpeConfigBuilder {
+        SharpeConfigBuilder::default()
+    }
+
+    /// Returns the configured risk-free rate or `constants::DEFAULT_RISK_FREE_RATE`.
+    pub fn risk_free_rate(&self) -> f64 {
+        self.risk_free_rate
+            .unwrap_or(constants::DEFAULT_RISK_FREE_RATE)
+    }
+}
+
+/// Builds a `SharpeConfig`.
+#[derive(Debug, Default)]
+pub struct SharpeConfigBuilder {
+    config: SharpeConfig,
+}
+
+impl SharpeConfigBuilder {
+    pub fn risk_free_rate(mut self, risk_free_rate: f64) -> Self {
+        self.config.risk_free_rate = Some(risk_free_rate);
+        self
+    }
+
+    pub fn min_candles(mut self, min_candles: usize) -> Self {
+        self.config.min_candles = min_candles;
+        self
+    }
+
+    pub fn build(self) -> SharpeConfig {
+        self.config
+    }
+}
+
 /// Represents the side of an option (call or put).
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum OptionChainSide {
     Call,
     Put,
 }
 
-impl From<&OptionChainSide> for String {
-    fn from(value: &OptionChainSide) -> Self {
-        match value {
-            OptionChainSide::Call => "call".to_string(),
-            OptionChainSide::Put => "put".to_string(),
+impl FromStr for OptionChainSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "call" => Ok(OptionChainSide::Call),
+            "put" => Ok(OptionChainSide::Put),
+            _ => Err(format!("unknown option side {}, expected call or put", s)),
+        }
+    }
+}
+
+impl OptionChainSide {
+    /// Iterates over both sides, puts first, for pulling calls and puts together.
+    pub fn iter_sides() -> impl Iterator<Item = OptionChainSide> {
+        [OptionChainSide::Put, OptionChainSide::Call].into_iter()
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OptionChainSide::Call => "call",
+            OptionChainSide::Put => "put",
         }
     }
 }
 
+impl Display for OptionChainSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Padded so that width and alignment apply in tables.
+        f.pad(self.as_str())
+    }
+}
+
 impl ToSql for OptionChainSide {
     fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
         match self {
@@ -86,26 +291,208 @@ impl FromSql for OptionChainSide {
 }
 
 /// Structure representing a candle for an option strike.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct OptionStrikeCandle {
-    pub underlying: String,    // Underlying asset symbol.
+    pub underlying: String,        // Underlying asset symbol.
+    pub strike: f64,               // Strike price.
+    pub underlying_price: f64,     // Underlying asset price.
+    pub vwap: f64,                 // Latest rolling VWAP of the underlying (0 when unknown).
+    pub side: OptionChainSide,     // Call or Put.
+    pub bid: f64,                  // Bid price.
+    pub mid: f64,                  // Mid price.
+    pub ask: f64,                  // Ask price.
+    pub bid_size: u32,             // Bid size.
+    pub ask_size: u32,             // Ask size.
+    pub last: f64,                 // Last traded price.
+    pub expiration: String,        // Expiration date and time.
+    pub updated: String,           // Last updated date and time.
+    pub dte: u32,                  // Days to expiration.
+    pub volume: u32,               // Volume.
+    pub open_interest: u32,        // Open interest.
+    pub rate_of_return: f64,       // Rate of return.
+    pub liquidity_score: f64,      // Composite of spread tightness, open interest and volume.
+    pub strike_from: f64,          // Strike price from.
+    pub strike_to: f64,            // Strike price to.
+    pub implied_volatility: f64,   // Implied volatility (0 when unknown).
+    pub delta: f64,                // Delta per share (0 when unknown).
+    pub theta: f64,                // Theta per share per day (0 when unknown).
+    pub vega: f64,                 // Vega per share (0 when unknown).
+    pub iv_hv_ratio: f64,          // Implied over historical volatility (0 when unknown).
+    pub implied_move_pct: f64,     // Straddle-implied move of the underlying (0 when unknown).
+    pub rolling_sharpe_252: f64,   // Latest 252-day rolling Sharpe ratio of the underlying.
+    pub atr_trend: f64,            // Slope of the underlying's recent EMA ATR values.
+    pub roc: f64,                  // Rate of change of the underlying's close (0 when unknown).
+    pub max_dd_duration_days: u32, // Longest drawdown of the underlying in calendar days.
+    pub recovery_factor: f64,      // Total return of the underlying over its maximum drawdown.
+    pub signal: String,            // Put-selling signal of the underlying.
+    pub earnings_risk: bool,       // The underlying reports earnings before expiration.
+    pub cagr: f64,                 // Compound annual growth rate of the underlying.
+}
+
+/// Identifies a contract by underlying, strike in thousandths, side and expiration, so that
+/// strikes can be compared without comparing floats.
+pub fn option_strike_candle_key(c: &OptionStrikeCandle) -> (&str, i64, &'static str, &str) {
+    (
+        &c.underlying,
+        (c.strike * 1000.0).round() as i64,
+        c.side.as_str(),
+        &c.expiration,
+    )
+}
+
+/// Scores how easily a strike can be traded as spread tightness `1 / (1 + (ask - bid) / mid)`
+/// times depth `ln(1 + open_interest)` times activity `ln(1 + volume)`. Higher is better and
+/// a strike without a mid price scores 0.
+pub fn option_liquidity_score(candle: &OptionStrikeCandle) -> f64 {
+    if candle.mid <= 0.0 {
+        return 0.0;
+    }
+    let tightness = 1.0 / (1.0 + (candle.ask - candle.bid) / candle.mid);
+    let depth = (1.0 + candle.open_interest as f64).ln();
+    let activity = (1.0 + candle.volume as f64).ln();
+    tightness * depth * activity
+}
+
+/// A strike holding an unusually large share of its underlying's open interest.
+#[derive(Debug)]
+pub struct OiConcentration {
+    pub symbol: String,        // Underlying asset symbol.
     pub strike: f64,           // Strike price.
-    pub underlying_price: f64, // Underlying asset price.
     pub side: OptionChainSide, // Call or Put.
-    pub bid: f64,              // Bid price.
-    pub mid: f64,              // Mid price.
-    pub ask: f64,              // Ask price.
-    pub bid_size: u32,         // Bid size.
-    pub ask_size: u32,         // Ask size.
-    pub last: f64,             // Last traded price.
-    pub expiration: String,    // Expiration date and time.
-    pub updated: String,       // Last updated date and time.
-    pub dte: u32,              // Days to expiration.
-    pub volume: u32,           // Volume.
-    pub open_interest: u32,    // Open interest.
-    pub rate_of_return: f64,   // Rate of return.
-    pub strike_from: f64,      // Strike price from.
-    pub strike_to: f64,        // Strike price to.
+    pub oi_pct: f64,           // Share of the underlying's total open interest.
+    pub open_interest: u32,    // Open interest summed across expirations.
+}
+
+/// Row count and estimated size of each table of the database.
+#[derive(Debug)]
+pub struct DbStats {
+    pub tables: Vec<TableStats>,
+}
+
+impl DbStats {
+    /// Estimated size of all tables in bytes.
+    pub fn total_size_bytes(&self) -> u64 {
+        self.tables.iter().map(|t| t.size_bytes).sum()
+    }
+}
+
+#[derive(Debug)]
+pub struct TableStats {
+    pub name: String,    // Table name.
+    pub row_count: u64,  // Number of rows.
+    pub size_bytes: u64, // Pages used by the table, or the length of its values without dbstat.
+}
+
+/// Aggregated statistics of the candles stored for a symbol. Everything but the symbol is 0
+/// when there are no candles.
+#[derive(Debug)]
+pub struct CandleSummary {
+    pub symbol: String,
+    pub count: u32,     // Number of candles.
+    pub oldest_ts: u32, // Timestamp of the oldest candle.
+    pub newest_ts: u32, // Timestamp of the newest candle.
+    pub avg_close: f64, // Average close.
+    pub min_close: f64, // Lowest close.
+    pub max_close: f64, // Highest close.
+}
+
+/// Symbols that succeeded and failed during a batch subcommand.
+#[derive(Debug, Default)]
+pub struct RunSummary {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<(String, String)>, // Symbol and the reason it failed.
+}
+
+impl RunSummary {
+    pub fn success(&mut self, symbol: &str) {
+        self.succeeded.push(symbol.to_string());
+    }
+
+    pub fn failure(&mut self, symbol: &str, reason: impl Display) {
+        self.failed.push((symbol.to_string(), reason.to_string()));
+    }
+
+    /// Logs how many symbols succeeded and which failed. The run only fails, with a
+    /// `PartialFailure`, when no symbol succeeded.
+    pub fn finish(self) -> Result<()> {
+        let failed_list = self
+            .failed
+            .iter()
+            .map(|(symbol, _)| symbol.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        log::info!(
+            "Run complete: {}/{} symbols succeeded. Failed: {}",
+            self.succeeded.len(),
+            self.succeeded.len() + self.failed.len(),
+            if failed_list.is_empty() {
+                "none"
+            } else {
+                &failed_list
+            }
+        );
+        if self.succeeded.is_empty() && !self.failed.is_empty() {
+            return Err(QuotesError::PartialFailure(
+                self.failed
+                    .into_iter()
+                    .map(|(symbol, reason)| format!("[{}] {}", symbol, reason))
+                    .collect(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The risk metrics of a symbol in one place.
+#[derive(Debug, Serialize)]
+pub struct RiskMetrics {
+    pub symbol: String,
+    pub sharpe: Option<f64>,  // Latest 252-day rolling Sharpe ratio.
+    pub sortino: Option<f64>, // Sortino ratio.
+    pub calmar: Option<f64>,  // Calmar ratio.
+    pub var_95: Option<f64>,  // 95% value at risk of daily returns.
+    pub cvar_95: Option<f64>, // 95% conditional value at risk of daily returns.
+    pub max_drawdown: f64,    // Maximum drawdown as a positive fraction of the peak.
+    pub hv_30: Option<f64>,   // Annualised historical volatility over the last 30 candles.
+}
+
+/// Outcome of replaying put sales over stored option chain snapshots.
+#[derive(Debug)]
+pub struct BacktestResult {
+    pub win_count: u32,  // Trades closed for less than the entry premium.
+    pub loss_count: u32, // Trades closed for at least the entry premium.
+    pub total_pnl: f64,  // Sum of per-share P&L.
+    pub max_loss: f64,   // Worst per-share P&L, 0 when no trade lost.
+    pub max_gain: f64,   // Best per-share P&L, 0 when no trade won.
+}
+
+/// An option position held in a portfolio.
+#[derive(Debug, Serialize)]
+pub struct PortfolioPosition {
+    pub candle: OptionStrikeCandle, // Latest stored quote of the contract.
+    pub contracts: i32,             // Number of contracts, negative when sold.
+    pub entry_mid: f64,             // Mid price per share when the position was opened.
+}
+
+/// A set of option positions with their aggregated Greeks, premium and P&L.
+#[derive(Debug, Serialize)]
+pub struct Portfolio {
+    pub positions: Vec<PortfolioPosition>,
+    pub net_delta: f64,                 // Net delta in shares.
+    pub net_theta: f64,                 // Net theta in dollars per day.
+    pub net_vega: f64,                  // Net vega in dollars per volatility point.
+    pub total_premium: f64,             // Premium received (positive) or paid (negative) at entry.
+    pub unrealized_pnl: f64,            // Gain or loss at the latest stored mid prices.
+    pub risk_metrics: Vec<RiskMetrics>, // Risk metrics of each underlying.
+}
+
+/// A file or folder in a Dropbox folder listing.
+#[derive(Debug)]
+pub struct DropboxEntry {
+    pub name: String,     // File name.
+    pub path: String,     // Full path for display.
+    pub size: u64,        // Size in bytes, 0 for folders.
+    pub modified: String, // Server modification time, empty for folders.
 }
 
 pub fn option_chain_to_csv_vec(all_chains: &[OptionStrikeCandle]) -> Result<Vec<u8>> {
@@ -114,13 +501,53 @@ pub fn option_chain_to_csv_vec(all_chains: &[OptionStrikeCandle]) -> Result<Vec<
 
     // Write the data rows.
     for chain in all_chains {
-        writer.serialize(chain).map_err(QuotesError::CsvError)?;
+        writer.serialize(chain)?;
     }
 
     let bytes = writer.into_inner().unwrap().into_inner().unwrap();
     Ok(bytes)
 }
 
+/// Checks that the prices of a candle are positive and that its high and low bound its open
+/// and close.
+pub fn validate_candle(candle: &Candle) -> std::result::Result<(), String> {
+    let prices = [candle.open, candle.high, candle.low, candle.close];
+    if prices.iter().any(|price| *price <= 0.0) {
+        return Err("prices must be positive".to_string());
+    }
+    if candle.high < candle.low {
+        return Err(format!("high {} below low {}", candle.high, candle.low));
+    }
+    if candle.high < candle.open.max(candle.close) {
+        return Err(format!("high {} below open or close", candle.high));
+    }
+    if candle.low > candle.open.min(candle.close) {
+        return Err(format!("low {} above open or close", candle.low));
+    }
+    Ok(())
+}
+
+/// Splits candles into the valid ones and a description of each invalid one.
+pub fn validate_candles(candles: &[Candle]) -> (Vec<Candle>, Vec<String>) {
+    let mut valid = Vec::with_capacity(candles.len());
+    let mut invalid = Vec::new();
+    for candle in candles {
+        match validate_candle(candle) {
+            Ok(()) => valid.push(candle.clone()),
+            Err(reason) => invalid.push(format!(
+                "{} at {}: {}",
+                candle.symbol, candle.timestamp, reason
+            )),
+        }
+    }
+    (valid, invalid)
+}
+
+/// Symbol of the candles, empty when there are none.
+pub fn symbol_of(candles: &[Candle]) -> &str {
+    candles.first().map_or("", |c| c.symbol.as_str())
+}
+
 pub type Result<T> = std::result::Result<T, QuotesError>;
 
 #[derive(Debug)]
@@ -129,21 +556,152 @@ pub enum QuotesError {
     CouldNotOpenFile(io::Error),
     CouldNotReadLine,
     EmptySymbolFile(String),
-    DatabaseError(rusqlite::Error),
-    HttpError(client::RequestError),
-    NotEnoughCandlesForStatistics(String),
+    // The symbol of these three is empty until the error is given one with `with_symbol`.
+    DatabaseError {
+        symbol: String,
+        source: rusqlite::Error,
+    },
+    HttpError {
+        symbol: String,
+        source: Box<client::RequestError>, // Boxed to keep the error small.
+    },
+    NotEnoughCandlesForStatistics {
+        symbol: String,
+        message: String,
+    },
+    SharpeCalculationError(String),
     CsvError(csv::Error),
+    JsonError(serde_json::Error),
     TelegramError(APIResponseError),
     EnvVarNotSet(VarError),
+    InvalidConfig(String),
+    PartialFailure(Vec<String>), // Per-symbol failures of an otherwise completed run.
+    RateLimitExceeded {
+        retry_after_secs: u64,
+    },
+    WithContext {
+        symbol: String,
+        source: Box<QuotesError>,
+    },
+}
+
+impl QuotesError {
+    /// Not enough candles, or values, of `symbol` for a statistic.
+    pub fn not_enough_candles(symbol: &str, message: impl Into<String>) -> QuotesError {
+        QuotesError::NotEnoughCandlesForStatistics {
+            symbol: symbol.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// Attaches the symbol that was being processed when the error occurred. Database, HTTP and
+    /// not enough candles errors keep it in their `symbol` field, unless they already have
+    /// one; other errors are wrapped in `WithContext`.
+    pub fn with_symbol(self, symbol: &str) -> QuotesError {
+        match self {
+            QuotesError::DatabaseError {
+                symbol: ref current,
+                ..
+            }
+            | QuotesError::HttpError {
+                symbol: ref current,
+                ..
+            }
+            | QuotesError::NotEnoughCandlesForStatistics {
+                symbol: ref current,
+                ..
+            } if !current.is_empty() => self,
+            QuotesError::DatabaseError { source, .. } => QuotesError::DatabaseError {
+                symbol: symbol.to_string(),
+                source,
+            },
+            QuotesError::HttpError { source, .. } => QuotesError::HttpError {
+                symbol: symbol.to_string(),
+                source,
+            },
+            QuotesError::NotEnoughCandlesForStatistics { message, .. } => {
+                QuotesError::not_enough_candles(symbol, message)
+            }
+            _ => QuotesError::WithContext {
+                symbol: symbol.to_string(),
+                source: Box::new(self),
+            },
+        }
+    }
+}
+
+// Prefixes a message with the symbol it is about, when known.
+fn write_with_symbol(
+    f: &mut std::fmt::Formatter<'_>,
+    symbol: &str,
+    message: std::fmt::Arguments,
+) -> std::fmt::Result {
+    match symbol.is_empty() {
+        true => write!(f, "{}", message),
+        false => write!(f, "[{}] {}", symbol, message),
+    }
+}
+
+/// Attaches the symbol being processed to the error of a result.
+pub trait WithSymbol<T> {
+    fn with_symbol(self, symbol: &str) -> Result<T>;
+}
+
+impl<T, E: Into<QuotesError>> WithSymbol<T> for std::result::Result<T, E> {
+    fn with_symbol(self, symbol: &str) -> Result<T> {
+        self.map_err(|err| err.into().with_symbol(symbol))
+    }
 }
 
 impl Display for QuotesError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+        match self {
+            QuotesError::FileNotFound(path) => write!(f, "File not found: {}", path),
+            QuotesError::CouldNotOpenFile(err) => write!(f, "Could not open file: {}", err),
+            QuotesError::CouldNotReadLine => write!(f, "Could not read line"),
+            QuotesError::EmptySymbolFile(path) => write!(f, "Symbol file is empty: {}", path),
+            QuotesError::DatabaseError { symbol, source } => {
+                write_with_symbol(f, symbol, format_args!("Database error: {}", source))
+            }
+            QuotesError::HttpError { symbol, source } => {
+                write_with_symbol(f, symbol, format_args!("HTTP error: {}", source))
+            }
+            QuotesError::NotEnoughCandlesForStatistics { symbol, message } => {
+                write_with_symbol(f, symbol, format_args!("{}", message))
+            }
+            QuotesError::SharpeCalculationError(msg) => {
+                write!(f, "Sharpe calculation error: {}", msg)
+            }
+            QuotesError::CsvError(err) => write!(f, "CSV error: {}", err),
+            QuotesError::JsonError(err) => write!(f, "JSON error: {}", err),
+            QuotesError::TelegramError(err) => write!(f, "Telegram error: {}", err),
+            QuotesError::EnvVarNotSet(err) => write!(f, "Environment variable not set: {}", err),
+            QuotesError::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            QuotesError::PartialFailure(failures) => {
+                write!(f, "Partial failure: {}", failures.join("; "))
+            }
+            QuotesError::RateLimitExceeded { retry_after_secs } => {
+                write!(f, "Rate limit exceeded, retry after {}s", retry_after_secs)
+            }
+            QuotesError::WithContext { symbol, source } => write!(f, "[{}] {}", symbol, source),
+        }
     }
 }
 
-impl Error for QuotesError {}
+impl Error for QuotesError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            QuotesError::CouldNotOpenFile(err) => Some(err),
+            QuotesError::DatabaseError { source, .. } => Some(source),
+            QuotesError::HttpError { source, .. } => Some(source.as_ref()),
+            QuotesError::CsvError(err) => Some(err),
+            QuotesError::JsonError(err) => Some(err),
+            QuotesError::EnvVarNotSet(err) => Some(err),
+            QuotesError::WithContext { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<VarError> for QuotesError {
     fn from(value: VarError) -> Self {
@@ -159,13 +717,36 @@ impl From<io::Error> for QuotesError {
 
 impl From<rusqlite::Error> for QuotesError {
     fn from(value: rusqlite::Error) -> Self {
-        Self::DatabaseError(value)
+        Self::DatabaseError {
+            symbol: String::new(),
+            source: value,
+        }
+    }
+}
+
+impl From<csv::Error> for QuotesError {
+    fn from(value: csv::Error) -> Self {
+        Self::CsvError(value)
+    }
+}
+
+impl From<serde_json::Error> for QuotesError {
+    fn from(value: serde_json::Error) -> Self {
+        Self::JsonError(value)
     }
 }
 
 impl From<client::RequestError> for QuotesError {
     fn from(value: client::RequestError) -> Self {
-        Self::HttpError(value)
+        match value {
+            client::RequestError::RateLimited { retry_after_secs } => {
+                Self::RateLimitExceeded { retry_after_secs }
+            }
+            _ => Self::HttpError {
+                symbol: String::new(),
+                source: Box::new(value),
+            },
+        }
     }
 }
 
@@ -174,3 +755,97 @@ impl From<APIResponseError> for QuotesError {
         Self::TelegramError(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(open: f64, high: f64, low: f64, close: f64, timestamp: u32) -> Candle {
+        Candle {
+            symbol: "AAPL".to_string(),
+            open,
+            high,
+            low,
+            close,
+            volume: 100,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn weekly_candle_of_no_candles_is_none() {
+        assert_eq!(Candle::from_weekly(&[]), None);
+    }
+
+    #[test]
+    fn weekly_candle_of_one_candle_is_that_candle() {
+        let day = candle(10.0, 12.0, 9.0, 11.0, 100);
+        assert_eq!(Candle::from_weekly(std::slice::from_ref(&day)), Some(day));
+    }
+
+    #[test]
+    fn weekly_candle_spans_five_days() {
+        let week = [
+            candle(10.0, 12.0, 9.0, 11.0, 100),
+            candle(11.0, 15.0, 10.0, 14.0, 200),
+            candle(14.0, 14.5, 8.0, 9.0, 300),
+            candle(9.0, 10.0, 8.5, 9.5, 400),
+            candle(9.5, 11.0, 9.0, 10.5, 500),
+        ];
+        assert_eq!(
+            Candle::from_weekly(&week),
+            Some(Candle {
+                volume: 500,
+                ..candle(10.0, 15.0, 8.0, 10.5, 100)
+            })
+        );
+    }
+
+    #[test]
+    fn option_chain_side_round_trips_through_its_string() {
+        for side in OptionChainSide::iter_sides() {
+            assert_eq!(side.to_string().parse::<OptionChainSide>(), Ok(side));
+        }
+        assert_eq!("PUT".parse::<OptionChainSide>(), Ok(OptionChainSide::Put));
+        assert_eq!(format!("{:>5}", OptionChainSide::Put), "  put");
+    }
+
+    #[test]
+    fn unknown_option_chain_side_is_an_error() {
+        assert_eq!(
+            "straddle".parse::<OptionChainSide>(),
+            Err("unknown option side straddle, expected call or put".to_string())
+        );
+    }
+
+    #[test]
+    fn with_symbol_fills_the_symbol_field_once() {
+        let err = QuotesError::from(rusqlite::Error::QueryReturnedNoRows).with_symbol("AAPL");
+        assert!(matches!(&err, QuotesError::DatabaseError { symbol, .. } if symbol == "AAPL"));
+        assert!(err.to_string().starts_with("[AAPL] Database error: "));
+        // The symbol nearest to the failure is kept.
+        let err = err.with_symbol("MSFT");
+        assert!(matches!(&err, QuotesError::DatabaseError { symbol, .. } if symbol == "AAPL"));
+    }
+
+    #[test]
+    fn not_enough_candles_takes_the_symbol_of_the_candles() {
+        let candles = [candle(10.0, 11.0, 9.0, 10.0, 100)];
+        let err = crate::volatility::historical_volatility(&candles).unwrap_err();
+        assert!(matches!(
+            &err,
+            QuotesError::NotEnoughCandlesForStatistics { symbol, .. } if symbol == "AAPL"
+        ));
+        assert_eq!(
+            QuotesError::not_enough_candles("", "Not enough values").to_string(),
+            "Not enough values"
+        );
+    }
+
+    #[test]
+    fn other_errors_are_wrapped_with_the_symbol() {
+        let err = QuotesError::InvalidConfig("bad".to_string()).with_symbol("AAPL");
+        assert!(matches!(&err, QuotesError::WithContext { symbol, .. } if symbol == "AAPL"));
+        assert_eq!(err.to_string(), "[AAPL] Invalid configuration: bad");
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    store::{candle, momentum},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Calculates the rate of change of every symbol and saves it to the database.
+/// Symbols in a downtrend are logged as warnings.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    period: u32,             // Number of candles to look back.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    momentum::create_table(conn)?;
+
+    let mut momentum_vec = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let roc = match calculate_roc(&candles, period) {
+            Ok(roc) => roc,
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping momentum");
+                continue;
+            }
+        };
+        if roc < constants::MOMENTUM_DOWNTREND_ROC {
+            log::warn!(
+                symbol = symbol.as_str(), period = period, roc = roc;
+                "Strongly negative rate of change, the underlying may be in a downtrend"
+            );
+        }
+        momentum_vec.push((symbol, roc, candles.last().unwrap().timestamp));
+    }
+
+    momentum::save_momentums(conn, period, &momentum_vec)?;
+    Ok(())
+}
+
+/// Rate of change of the last close over the close `period` candles earlier.
+/// Candles must be sorted from oldest to newest.
+pub fn calculate_roc(candles: &[model::Candle], period: u32) -> model::Result<f64> {
+    let period = period as usize;
+    if candles.len() <= period {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for rate of change calculation (period: {}, candles: {})",
+                period,
+                candles.len()
+            ),
+        ));
+    }
+    let current = candles[candles.len() - 1].close;
+    let previous = candles[candles.len() - 1 - period].close;
+    Ok((current - previous) / previous)
+}
@@ -1,76 +1,428 @@
 use core::str;
-use std::env;
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    io::Write,
+};
 
 use chrono::{DateTime, Datelike, Days, Local, Timelike, Weekday};
 use rusqlite::Connection;
 use telegram_bot_api::{
     bot,
-    types::{ChatId, InputFile},
+    types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, ReplyMarkup},
 };
 
 use crate::{
-    constants,
+    atr,
+    config::Config,
+    constants, discord, dropbox, earnings,
     marketdata::api_caller,
-    model::{self, QuotesError},
-    store::{candle, option_chain, true_range},
-    symbols,
+    model::{self, QuotesError, WithSymbol},
+    signal, slack,
+    store::{
+        cagr, candle, implied_move, momentum, option_chain, recovery_factor, risk_metrics,
+        rolling_sharpe, runs, streaks, true_range, vwap,
+    },
+    symbols, template, volatility,
 };
 
+/// Thresholds used to flag notable chains when publishing.
+#[derive(clap::Args, Debug, Clone)]
+pub struct AlertConfig {
+    /// Flag strikes holding more than this share of the underlying's open interest.
+    #[arg(long, default_value_t = constants::OI_CONCENTRATION_THRESHOLD)]
+    pub oi_concentration_threshold: f64,
+    /// Warn when implied volatility falls below this multiple of historical volatility.
+    #[arg(long)]
+    pub iv_hv_alert: Option<f64>,
+    /// Leave the best put of each symbol out of the Telegram alert message.
+    #[arg(long)]
+    pub hide_best_options: bool,
+}
+
+/// Column the published chains are ordered by.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    /// Best rate of return first.
+    RateOfReturn,
+    Strike,
+    Dte,
+    OpenInterest,
+    /// Bid-ask spread relative to the mid price, tightest first.
+    BidAskSpread,
+}
+
+/// Sorts the chains by a column, ascending except for the rate of return which is sorted
+/// best first. Ties are ordered by underlying.
+pub fn sort_chains(chains: &mut [model::OptionStrikeCandle], sort_by: SortBy) {
+    let spread = |c: &model::OptionStrikeCandle| (c.ask - c.bid) / c.mid;
+    chains.sort_by(|a, b| {
+        match sort_by {
+            SortBy::RateOfReturn => b.rate_of_return.total_cmp(&a.rate_of_return),
+            SortBy::Strike => a.strike.total_cmp(&b.strike),
+            SortBy::Dte => a.dte.cmp(&b.dte),
+            SortBy::OpenInterest => a.open_interest.cmp(&b.open_interest),
+            SortBy::BidAskSpread => spread(a).total_cmp(&spread(b)),
+        }
+        .then_with(|| a.underlying.cmp(&b.underlying))
+    });
+}
+
+// Expiries to pull and bounds applied to pulled chains before they are saved.
+#[derive(clap::Args, Debug, Clone)]
+pub struct OptionChainFilterConfig {
+    /// Target days to expiration, 1 to 365, instead of the next weekly expiry. Must not be
+    /// above --max-dte.
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=365))]
+    pub expiry_dte: Option<u32>,
+    /// Drop chains expiring in fewer days than this.
+    #[arg(long, default_value_t = constants::MIN_DTE)]
+    pub min_dte: u32,
+    /// Drop chains expiring in more days than this.
+    #[arg(long, default_value_t = constants::MAX_DTE)]
+    pub max_dte: u32,
+    /// Drop chains with a lower liquidity score.
+    #[arg(long, default_value_t = 0.0)]
+    pub min_liquidity_score: f64,
+    /// CSV of `symbol,earnings_timestamp` rows used to flag chains expiring after earnings.
+    #[arg(long)]
+    pub earnings_calendar: Option<String>,
+}
+
+/// Keeps the chains whose days to expiration are within the configured bounds, inclusive,
+/// and whose liquidity score is at least the configured minimum. Chains whose underlying
+/// reports earnings before they expire are kept but flagged with `earnings_risk`.
+pub fn filter_option_chains(
+    chains: Vec<model::OptionStrikeCandle>,
+    config: &OptionChainFilterConfig,
+    earnings_map: &HashMap<String, Vec<u32>>,
+) -> Vec<model::OptionStrikeCandle> {
+    chains
+        .into_iter()
+        .filter(|chain| !(chain.dte < config.min_dte || chain.dte > config.max_dte))
+        .filter(|chain| chain.liquidity_score >= config.min_liquidity_score)
+        .map(|mut chain| {
+            chain.earnings_risk =
+                earnings::expiration_timestamp(&chain.expiration).is_some_and(|expiry_ts| {
+                    earnings::has_earnings_before_expiration(
+                        &chain.underlying,
+                        expiry_ts,
+                        earnings_map,
+                    )
+                });
+            chain
+        })
+        .collect()
+}
+
+/// Parses `call`, `put` or `both` into the sides to pull.
+pub fn parse_sides(value: &str) -> Result<Vec<model::OptionChainSide>, String> {
+    match value.to_lowercase().as_str() {
+        "both" => Ok(model::OptionChainSide::iter_sides().collect()),
+        side => side
+            .parse()
+            .map(|side| vec![side])
+            .map_err(|_| format!("unknown option side {}, expected call, put or both", value)),
+    }
+}
+
 /// Pulls option chains from the API based on ranges of symbols from the database.
+/// Symbols without a true range or a candle, or whose chains cannot be pulled, saved or
+/// enriched, are skipped. The run is still completed, and only fails, with a `PartialFailure`,
+/// when every symbol did.
 pub async fn retrieve_option_chains_base_on_ranges(
     symbols_file_path: &str, // Path to the file containing symbols.
-    side: &model::OptionChainSide,
-    mut conn: Connection, // Database connection.
+    sides: &[model::OptionChainSide],
+    conn: &mut Connection, // Database connection.
+    config: &Config,
+    alerts: &AlertConfig,
+    filter: &OptionChainFilterConfig,
+    publish: bool, // Send the pulled chains to Telegram.
 ) -> model::Result<()> {
+    // Otherwise every pulled chain would be filtered out.
+    if let Some(expiry_dte) = filter.expiry_dte.filter(|&dte| dte > filter.max_dte) {
+        return Err(model::QuotesError::InvalidConfig(format!(
+            "expiry DTE {} is above the max DTE {}",
+            expiry_dte, filter.max_dte
+        )));
+    }
     let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+    let earnings_map = match &filter.earnings_calendar {
+        Some(path) => earnings::load_earnings_calendar(path)?,
+        None => HashMap::new(),
+    };
 
     // Initialize the option_strike table in the database.
-    option_chain::create_table(&conn)?;
+    option_chain::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+    streaks::create_table(conn)?;
+    recovery_factor::create_table(conn)?;
+    vwap::create_table(conn)?;
+    momentum::create_table(conn)?;
+    cagr::create_table(conn)?;
+    implied_move::create_table(conn)?;
+
+    let command = env::args().collect::<Vec<_>>().join(" ");
+    let run_id = runs::start_run(conn, &command)?;
+    log::info!(run_id = run_id.as_str(); "Started option chain run");
 
     let mut all_chains: Vec<model::OptionStrikeCandle> = Vec::with_capacity(100);
 
+    let symbol_refs: Vec<&str> = symbols.iter().map(String::as_str).collect();
+    let true_ranges = true_range::get_true_ranges_bulk(conn, &symbol_refs)?;
+
+    let mut summary = model::RunSummary::default();
     for symbol in symbols {
-        let true_range_ratio = true_range::get_true_range(&conn, &symbol)?;
-        let latest_candle = &candle::get_candles(&conn, &symbol, 1)?[0];
-        let safety_range =
-            (true_range_ratio.percentile_range - true_range_ratio.ema_range).abs() * 0.1;
-        let v1 = latest_candle.close * (1.0 - true_range_ratio.ema_range);
-        let v2 = latest_candle.close * (1.0 - true_range_ratio.percentile_range);
-        let mut strike_range = match v1 < v2 {
-            true => (v1, v2),
-            false => (v2, v1),
-        }; // (smaller,bigger)
-        strike_range.1 *= 1.0 - safety_range; // decrement bigger value by safety_range
-
-        let chains = api_caller::option_chain(
-            &symbol,
-            strike_range,
-            &get_expiration_date_range(),
-            constants::MIN_OPEN_INTEREST,
-            side,
+        let Some(true_range_ratio) = true_ranges.get(&symbol) else {
+            log::error!(symbol = symbol.as_str(); "No true range, run CalculateAtr first");
+            summary.failure(&symbol, "no true range");
+            continue;
+        };
+        let latest_candle = match candle::get_latest_candle(conn, &symbol) {
+            Ok(candle) => candle,
+            Err(err) => {
+                log::error!(symbol = symbol.as_str(), err:% = err; "Fail to get latest candle");
+                summary.failure(&symbol, err);
+                continue;
+            }
+        };
+        // A symbol counts as failed once, however many of its sides failed.
+        let mut side_errors: Vec<String> = Vec::new();
+        for side in sides {
+            let chains = api_caller::option_chain(
+                &symbol,
+                strike_range(
+                    latest_candle.close,
+                    true_range_ratio,
+                    side,
+                    filter.expiry_dte,
+                ),
+                &get_expiration_date_range(filter.expiry_dte),
+                config.min_open_interest,
+                side,
+            )
+            .await;
+
+            match chains {
+                Ok(chains) => {
+                    let pulled = chains.len();
+                    let mut chains = filter_option_chains(chains, filter, &earnings_map);
+                    if chains.len() < pulled {
+                        log::info!(
+                            symbol = symbol.as_str(), dropped = pulled - chains.len();
+                            "Dropped chains outside the DTE and liquidity bounds"
+                        );
+                    }
+                    let at_risk = chains.iter().filter(|chain| chain.earnings_risk).count();
+                    if at_risk > 0 {
+                        log::warn!(
+                            symbol = symbol.as_str(), chains = at_risk;
+                            "Chains expire after an earnings event"
+                        );
+                    }
+                    // save to DB
+                    if let Err(err) = option_chain::save_option_strike(conn, &chains, &run_id).await
+                    {
+                        log::error!(
+                            symbol = symbol.as_str(), side:% = side, err:% = err;
+                            "Fail to save option chain"
+                        );
+                        side_errors.push(format!("{}: {}", side, err));
+                        continue;
+                    }
+                    if let Err(err) = enrich_chains(conn, &symbol, &mut chains, config) {
+                        log::warn!(
+                            symbol = symbol.as_str(), side:% = side, err:% = err;
+                            "Fail to enrich option chain"
+                        );
+                        side_errors.push(format!("{}: {}", side, err));
+                        continue;
+                    }
+                    all_chains.extend(chains);
+                }
+                Err(e) => {
+                    log::error!(
+                        symbol = symbol.as_str(), side:% = side, err:% = e;
+                        "Fail to retrieve option chain"
+                    );
+                    side_errors.push(format!("{}: {}", side, e));
+                }
+            }
+        }
+        match side_errors.is_empty() {
+            true => summary.success(&symbol),
+            false => summary.failure(&symbol, side_errors.join("; ")),
+        }
+    }
+    runs::complete_run(conn, &run_id)?;
+
+    if publish {
+        publish_notifications(&all_chains, alerts).await?;
+    }
+    summary.finish()
+}
+
+/// Returns the `(smaller, bigger)` out-of-the-money strike range: the close moved down for puts
+/// or up for calls by the EMA and percentile true ranges. The end nearer the money is pushed
+/// away from it by a tenth of the gap between the two ranges. The true ranges are weekly, so
+/// with `expiry_dte` they are scaled by the square root of the number of weeks to expiry.
+fn strike_range(
+    close: f64,
+    true_range: &model::TrueRange,
+    side: &model::OptionChainSide,
+    expiry_dte: Option<u32>,
+) -> (f64, f64) {
+    let scale = expiry_dte.map_or(1.0, |dte| (dte as f64 / 7.0).sqrt());
+    let ema_range = true_range.ema_range * scale;
+    let percentile_range = true_range.percentile_range * scale;
+    let safety_range = (percentile_range - ema_range).abs() * 0.1;
+    let direction = match side {
+        model::OptionChainSide::Put => -1.0,
+        model::OptionChainSide::Call => 1.0,
+    };
+    let v1 = close * (1.0 + direction * ema_range);
+    let v2 = close * (1.0 + direction * percentile_range);
+    let mut strike_range = match v1 < v2 {
+        true => (v1, v2),
+        false => (v2, v1),
+    }; // (smaller,bigger)
+    match side {
+        model::OptionChainSide::Put => strike_range.1 *= 1.0 - safety_range, // decrement bigger value by safety_range
+        model::OptionChainSide::Call => strike_range.0 *= 1.0 + safety_range, // increment smaller value by safety_range
+    }
+    strike_range
+}
+
+/// Fills in the per-symbol metrics of the chains that are derived from stored candles.
+fn enrich_chains(
+    conn: &Connection,
+    symbol: &str,
+    chains: &mut [model::OptionStrikeCandle],
+    config: &Config,
+) -> model::Result<()> {
+    SymbolMetrics::load(conn, symbol, config)?.apply(chains, config);
+    Ok(())
+}
+
+/// Metrics of a symbol derived from stored candles, read once and copied onto each of its
+/// chains.
+struct SymbolMetrics {
+    rolling_sharpe_252: f64,
+    atr_trend: f64,
+    max_dd_duration_days: u32,
+    recovery_factor: f64,
+    vwap: f64,
+    roc: f64,
+    implied_move_pct: f64,
+    cagr: f64,
+    indicators: Option<(f64, signal::BollingerBands)>, // RSI and Bollinger bands.
+    hv: Option<f64>,                                   // Historical volatility.
+}
+
+impl SymbolMetrics {
+    fn load(conn: &Connection, symbol: &str, config: &Config) -> model::Result<Self> {
+        let risk = risk_metrics::get_risk_metrics(
+            conn,
+            symbol,
+            config.candle_count,
+            config.risk_free_rate,
         )
-        .await;
+        .with_symbol(symbol)?;
+        let true_ranges = true_range::get_true_range_rolling(
+            conn,
+            true_range::RATIO_TABLE,
+            symbol,
+            constants::ATR_TREND_WINDOW,
+        )
+        .with_symbol(symbol)?;
+        let indicators = signal::indicators(conn, symbol).with_symbol(symbol)?;
+        if indicators.is_none() {
+            log::warn!(symbol = symbol; "Not enough candles, skipping signal");
+        }
 
-        match chains {
-            Ok(chains) => {
-                // save to DB
-                option_chain::save_option_strike(&mut conn, &chains)?;
-                all_chains.extend(chains);
+        // Historical volatility over a fixed calendar window rather than a fixed candle count.
+        let to = Local::now();
+        let from = to - Days::new(constants::HV_WINDOW_DAYS);
+        let candles = candle::get_candles_in_date_range(
+            conn,
+            symbol,
+            from.timestamp() as u32,
+            to.timestamp() as u32,
+        )
+        .with_symbol(symbol)?;
+        let hv = match volatility::historical_volatility(&candles) {
+            Ok(hv) => Some(hv),
+            Err(err) => {
+                log::warn!(symbol = symbol, err:% = err; "Skipping volatility metrics");
+                None
             }
-            Err(e) => {
-                log::error!("Fail to retrieve option chain for {}. Err: {}", symbol, e);
+        };
+
+        Ok(SymbolMetrics {
+            rolling_sharpe_252: risk.sharpe.unwrap_or(0.0),
+            atr_trend: atr::atr_trend(&true_ranges),
+            max_dd_duration_days: streaks::get_max_dd_duration_days(conn, symbol)
+                .with_symbol(symbol)?
+                .unwrap_or(0),
+            recovery_factor: recovery_factor::get_recovery_factor(conn, symbol)
+                .with_symbol(symbol)?
+                .unwrap_or(0.0),
+            vwap: vwap::get_latest_vwap(conn, symbol, constants::VWAP_WINDOW)
+                .with_symbol(symbol)?
+                .unwrap_or(0.0),
+            roc: momentum::get_momentum(conn, symbol, constants::MOMENTUM_PERIOD)
+                .with_symbol(symbol)?
+                .unwrap_or(0.0),
+            implied_move_pct: implied_move::get_latest_implied_move(conn, symbol)
+                .with_symbol(symbol)?
+                .unwrap_or(0.0),
+            cagr: cagr::get_cagr(conn, symbol)
+                .with_symbol(symbol)?
+                .unwrap_or(0.0),
+            indicators,
+            hv,
+        })
+    }
+
+    fn apply(&self, chains: &mut [model::OptionStrikeCandle], config: &Config) {
+        for chain in chains.iter_mut() {
+            chain.vwap = format!("{:.2}", self.vwap).parse().unwrap();
+            chain.roc = format!("{:.4}", self.roc).parse().unwrap();
+            chain.cagr = format!("{:.4}", self.cagr).parse().unwrap();
+            chain.implied_move_pct = format!("{:.4}", self.implied_move_pct).parse().unwrap();
+            chain.max_dd_duration_days = self.max_dd_duration_days;
+            chain.recovery_factor = format!("{:.3}", self.recovery_factor).parse().unwrap();
+            chain.rolling_sharpe_252 = format!("{:.3}", self.rolling_sharpe_252).parse().unwrap();
+            chain.atr_trend = format!("{:.5}", self.atr_trend).parse().unwrap();
+            if let Some((rsi, bollinger)) = self.indicators {
+                chain.signal =
+                    signal::generate_signal(chain, self.rolling_sharpe_252, rsi, bollinger, config)
+                        .to_string();
+            }
+            if let Some(hv) = self.hv.filter(|hv| *hv > 0.0) {
+                if chain.implied_volatility > 0.0 {
+                    chain.iv_hv_ratio = format!("{:.3}", chain.implied_volatility / hv)
+                        .parse()
+                        .unwrap();
+                }
             }
         }
     }
-
-    publish_to_telegram(&all_chains).await
 }
 
 /// Calculates the range of expiration dates to use when fetching option chains.
-/// The range is determined based on the current day of the week.
+/// The range is determined based on the current day of the week, or starts `expiry_dte` days
+/// from now and spans `EXPIRY_DTE_WINDOW_DAYS` so that it holds a weekly expiry.
 /// Returns a tuple containing the start and end dates of the expiration date range.
-fn get_expiration_date_range() -> (DateTime<Local>, DateTime<Local>) {
+fn get_expiration_date_range(expiry_dte: Option<u32>) -> (DateTime<Local>, DateTime<Local>) {
     let now = Local::now().with_hour(12).unwrap();
+    if let Some(dte) = expiry_dte {
+        let target = now + Days::new(dte as u64);
+        return (
+            target,
+            target + Days::new(constants::EXPIRY_DTE_WINDOW_DAYS),
+        );
+    }
     match now.weekday() {
         Weekday::Mon => (now + Days::new(3), now + Days::new(3 + 2)), // Thur to Sat
         Weekday::Tue => (now + Days::new(2), now + Days::new(2 + 7)), // Thur to next Sat
@@ -82,36 +434,644 @@ fn get_expiration_date_range() -> (DateTime<Local>, DateTime<Local>) {
     }
 }
 
+/// Publishes the latest stored option chains of the symbols. With `best_per_symbol`, only the
+/// put with the highest rate of return at or above the given minimum is published for each
+/// symbol. Otherwise each snapshot is read `OPTION_CHAIN_PAGE_SIZE` strikes at a time and
+/// streamed to the CSV. Symbols whose chains cannot be read or enriched are left out, and the
+/// run only fails, with a `PartialFailure`, when every symbol did.
 pub async fn publish_option_chains(
     symbols_file_path: &str, // Path to the file containing symbols.
-    mut conn: Connection,    // Database connection.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+    alerts: &AlertConfig,
+    sort_by: Option<SortBy>, // Column to order the chains by, the stored order when None.
+    best_per_symbol: Option<f64>, // Minimum rate of return of the best put per symbol.
 ) -> model::Result<()> {
-    option_chain::create_table(&conn)?;
+    option_chain::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+    streaks::create_table(conn)?;
+    recovery_factor::create_table(conn)?;
+    vwap::create_table(conn)?;
+    momentum::create_table(conn)?;
+    cagr::create_table(conn)?;
+    implied_move::create_table(conn)?;
     let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
 
-    let mut all_chains: Vec<model::OptionStrikeCandle> = Vec::with_capacity(100);
+    if let Some(min_ror) = best_per_symbol {
+        let mut best_chains = option_chain::retrieve_best_option_per_symbol(
+            conn,
+            &model::OptionChainSide::Put,
+            min_ror,
+        )?;
+        best_chains.retain(|chain| symbols.contains(&chain.underlying));
+        let mut summary = model::RunSummary::default();
+        let mut enriched_chains = Vec::with_capacity(best_chains.len());
+        for mut chain in best_chains {
+            let symbol = chain.underlying.clone();
+            if let Err(err) = enrich_chains(conn, &symbol, std::slice::from_mut(&mut chain), config)
+            {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "fail to enrich chain");
+                summary.failure(&symbol, err);
+                continue;
+            }
+            summary.success(&symbol);
+            enriched_chains.push(chain);
+        }
+        if let Some(sort_by) = sort_by {
+            sort_chains(&mut enriched_chains, sort_by);
+        }
+        publish_notifications(&enriched_chains, alerts).await?;
+        return summary.finish();
+    }
+
+    // Snapshots are read in pages. Each page is written to the CSV and folded into the digest
+    // before the next is read, unless the chains are sorted, which needs all of them at once.
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut digest = ChainDigest::new(alerts.iv_hv_alert);
+    let mut to_sort: Vec<model::OptionStrikeCandle> = Vec::new();
+    let mut summary = model::RunSummary::default();
     for symbol in symbols {
-        let chains = option_chain::retrieve_option_chain(&mut conn, &symbol);
-        match chains {
-            Ok(chains) => all_chains.extend(chains),
+        let (run_id, updated) = match option_chain::get_latest_snapshot(conn, &symbol) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                log::error!(symbol = symbol.as_str(), err:% = err; "fail to retrieve chain");
+                summary.failure(&symbol, err);
+                continue;
+            }
+        };
+        let metrics = match SymbolMetrics::load(conn, &symbol, config) {
+            Ok(metrics) => metrics,
             Err(err) => {
-                log::error!("fail to retrieve chain for {}. Error: {}.", symbol, err);
+                log::warn!(symbol = symbol.as_str(), err:% = err; "fail to enrich chain");
+                summary.failure(&symbol, err);
                 continue;
             }
         };
+        let count =
+            match option_chain::retrieve_option_chain_count(conn, &symbol, &run_id, &updated) {
+                Ok(count) => count as usize,
+                Err(err) => {
+                    log::error!(symbol = symbol.as_str(), err:% = err; "fail to retrieve chain");
+                    summary.failure(&symbol, err);
+                    continue;
+                }
+            };
+        let mut page_error = None;
+        for page in 0..count.div_ceil(constants::OPTION_CHAIN_PAGE_SIZE) {
+            let mut chains = match option_chain::retrieve_option_chain_page(
+                conn,
+                &symbol,
+                &run_id,
+                &updated,
+                page,
+                constants::OPTION_CHAIN_PAGE_SIZE,
+            ) {
+                Ok(chains) => chains,
+                Err(err) => {
+                    page_error = Some(err);
+                    break;
+                }
+            };
+            metrics.apply(&mut chains, config);
+            for chain in &chains {
+                digest.add(chain);
+            }
+            match sort_by {
+                Some(_) => to_sort.extend(chains),
+                None => {
+                    for chain in &chains {
+                        writer.serialize(chain)?;
+                    }
+                }
+            }
+        }
+        match page_error {
+            // The pages read before the error are still published.
+            Some(err) => {
+                log::error!(symbol = symbol.as_str(), err:% = err; "fail to retrieve chain");
+                summary.failure(&symbol, err);
+            }
+            None => summary.success(&symbol),
+        }
+    }
+
+    if let Some(sort_by) = sort_by {
+        sort_chains(&mut to_sort, sort_by);
+        for chain in &to_sort {
+            writer.serialize(chain)?;
+        }
+    }
+    let csv = writer.into_inner().map_err(|err| err.into_error())?;
+    send_notifications(csv, &digest, alerts).await?;
+    summary.finish()
+}
+
+/// Deletes the stored option chains of the symbols in the file.
+/// Only snapshots taken before `before` are deleted when it is given.
+pub fn clear_option_chains(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    before: Option<&str>,    // `updated` cutoff, exclusive.
+    conn: &mut Connection,   // Database connection.
+) -> model::Result<()> {
+    option_chain::create_table(conn)?;
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    for symbol in symbols {
+        let deleted = match before {
+            Some(cutoff) => option_chain::delete_chains_before(conn, &symbol, cutoff),
+            None => option_chain::delete_all_chains_for_symbol(conn, &symbol),
+        }
+        .with_symbol(&symbol)?;
+        log::info!(symbol = symbol.as_str(), deleted = deleted; "Deleted option chain rows");
+    }
+    Ok(())
+}
+
+// Strikes of a snapshot to print.
+#[derive(clap::Args, Debug, Clone)]
+pub struct StrikeFilter {
+    /// Lowest strike to print.
+    #[arg(long)]
+    pub min_strike: Option<f64>,
+    /// Highest strike to print.
+    #[arg(long)]
+    pub max_strike: Option<f64>,
+    /// Only print this side: call or put.
+    #[arg(long)]
+    pub side: Option<model::OptionChainSide>,
+}
+
+impl StrikeFilter {
+    fn has_strike_range(&self) -> bool {
+        self.min_strike.is_some() || self.max_strike.is_some()
+    }
+
+    fn matches(&self, chain: &model::OptionStrikeCandle) -> bool {
+        self.min_strike.is_none_or(|min| chain.strike >= min)
+            && self.max_strike.is_none_or(|max| chain.strike <= max)
+            && self.side.as_ref().is_none_or(|side| &chain.side == side)
+    }
+}
+
+/// Prints an option chain snapshot of a symbol as CSV to stdout: the one saved by `run_id`
+/// when given, otherwise the latest run at `timestamp`, otherwise the latest snapshot.
+/// Only the strikes matching `filter` are printed.
+pub fn print_option_chain(
+    symbol: &str,
+    timestamp: Option<&str>, // `updated` value of the snapshot.
+    run_id: Option<&str>,    // Run that saved the snapshot.
+    filter: &StrikeFilter,
+    conn: &mut Connection, // Database connection.
+) -> model::Result<()> {
+    option_chain::create_table(conn)?;
+
+    let snapshots = option_chain::list_snapshot_times(conn, symbol)?;
+    let listed: Vec<String> = snapshots
+        .iter()
+        .map(|(run_id, updated)| format!("{} ({})", updated, run_id))
+        .collect();
+    log::info!(symbol = symbol; "Available snapshots: {}", listed.join(", "));
+
+    let chains = match (run_id, timestamp) {
+        (None, Some(timestamp)) => {
+            // Several runs may share a day; take the latest.
+            let run_id = snapshots
+                .iter()
+                .rev()
+                .find(|(_, updated)| updated == timestamp)
+                .map_or("", |(run_id, _)| run_id.as_str());
+            option_chain::get_option_chain_at(conn, symbol, run_id, timestamp)?
+        }
+        // The latest snapshot is filtered in the query.
+        (None, None) if filter.has_strike_range() => {
+            option_chain::get_option_chain_for_strike_range(
+                conn,
+                symbol,
+                filter.min_strike.unwrap_or(f64::NEG_INFINITY),
+                filter.max_strike.unwrap_or(f64::INFINITY),
+            )?
+        }
+        (None, None) if filter.side.is_some() => {
+            option_chain::get_option_chain_for_side(conn, symbol, filter.side.as_ref().unwrap())?
+        }
+        _ => option_chain::retrieve_option_chain(conn, symbol, run_id)?,
+    };
+    let chains: Vec<model::OptionStrikeCandle> = chains
+        .into_iter()
+        .filter(|chain| filter.matches(chain))
+        .collect();
+    let csv = model::option_chain_to_csv_vec(&chains)?;
+    std::io::stdout().write_all(&csv)?;
+    Ok(())
+}
+
+/// Flags strikes whose open interest exceeds `threshold` as a share of the underlying's total.
+/// Open interest is summed across expirations for the same underlying, side and strike.
+pub fn detect_oi_concentration(
+    chains: &[model::OptionStrikeCandle],
+    threshold: f64,
+) -> Vec<model::OiConcentration> {
+    let mut open_interest = OpenInterest::default();
+    for chain in chains {
+        open_interest.add(chain);
+    }
+    open_interest.concentrations(threshold)
+}
+
+/// Open interest of chains summed per underlying and per strike. Strikes are keyed by
+/// underlying, side and strike in thousandths, like `option_strike_candle_key`.
+#[derive(Default)]
+struct OpenInterest {
+    totals: HashMap<String, u32>,
+    strikes: HashMap<(String, &'static str, i64), (f64, model::OptionChainSide, u32)>,
+}
+
+impl OpenInterest {
+    fn add(&mut self, chain: &model::OptionStrikeCandle) {
+        *self.totals.entry(chain.underlying.clone()).or_default() += chain.open_interest;
+        let key = (
+            chain.underlying.clone(),
+            chain.side.as_str(),
+            (chain.strike * 1000.0).round() as i64,
+        );
+        self.strikes
+            .entry(key)
+            .or_insert((chain.strike, chain.side.clone(), 0))
+            .2 += chain.open_interest;
+    }
+
+    fn concentrations(&self, threshold: f64) -> Vec<model::OiConcentration> {
+        let mut concentrations: Vec<model::OiConcentration> = self
+            .strikes
+            .iter()
+            .filter_map(|((symbol, _, _), (strike, side, open_interest))| {
+                let total = self.totals[symbol];
+                if total == 0 {
+                    return None;
+                }
+                let oi_pct = *open_interest as f64 / total as f64;
+                (oi_pct > threshold).then(|| model::OiConcentration {
+                    symbol: symbol.clone(),
+                    strike: *strike,
+                    side: side.clone(),
+                    oi_pct,
+                    open_interest: *open_interest,
+                })
+            })
+            .collect();
+        concentrations.sort_by(|a, b| {
+            a.symbol
+                .cmp(&b.symbol)
+                .then(a.strike.total_cmp(&b.strike))
+                .then_with(|| a.side.as_str().cmp(b.side.as_str()))
+        });
+        concentrations
+    }
+}
+
+/// Aggregates of published chains built up one chain at a time, so that the chains themselves
+/// can be streamed to the CSV: the caption figures, the per-symbol summary, the best put of
+/// each symbol and the open interest per strike.
+struct ChainDigest {
+    iv_hv_alert: Option<f64>, // Warn about chains with a lower IV/HV ratio.
+    count: usize,
+    dte_sum: f64,
+    top_ror: f64,
+    expirations: Option<(String, String)>, // Earliest and latest expiration.
+    symbols: BTreeMap<String, (usize, f64)>, // Strike count and best rate of return.
+    best_puts: BTreeMap<String, model::OptionStrikeCandle>,
+    open_interest: OpenInterest,
+}
+
+impl ChainDigest {
+    fn new(iv_hv_alert: Option<f64>) -> Self {
+        ChainDigest {
+            iv_hv_alert,
+            count: 0,
+            dte_sum: 0.0,
+            top_ror: 0.0,
+            expirations: None,
+            symbols: BTreeMap::new(),
+            best_puts: BTreeMap::new(),
+            open_interest: OpenInterest::default(),
+        }
+    }
+
+    fn add(&mut self, chain: &model::OptionStrikeCandle) {
+        if let Some(threshold) = self.iv_hv_alert {
+            if chain.iv_hv_ratio > 0.0 && chain.iv_hv_ratio < threshold {
+                log::warn!(
+                    symbol = chain.underlying.as_str(),
+                    side:% = chain.side,
+                    strike = chain.strike,
+                    expiration = chain.expiration.as_str(),
+                    iv_hv_ratio = chain.iv_hv_ratio,
+                    threshold = threshold;
+                    "Option may be cheap: IV/HV ratio below threshold"
+                );
+            }
+        }
+
+        self.count += 1;
+        self.dte_sum += chain.dte as f64;
+        self.top_ror = self.top_ror.max(chain.rate_of_return);
+        match &mut self.expirations {
+            Some((first, last)) => {
+                if chain.expiration < *first {
+                    *first = chain.expiration.clone();
+                }
+                if chain.expiration > *last {
+                    *last = chain.expiration.clone();
+                }
+            }
+            None => {
+                self.expirations = Some((chain.expiration.clone(), chain.expiration.clone()));
+            }
+        }
+        let (count, best) = self
+            .symbols
+            .entry(chain.underlying.clone())
+            .or_insert((0, chain.rate_of_return));
+        *count += 1;
+        *best = best.max(chain.rate_of_return);
+
+        if chain.side == model::OptionChainSide::Put {
+            match self.best_puts.get_mut(&chain.underlying) {
+                Some(best) if chain.rate_of_return <= best.rate_of_return => {}
+                Some(best) => *best = chain.clone(),
+                None => {
+                    self.best_puts
+                        .insert(chain.underlying.clone(), chain.clone());
+                }
+            }
+        }
+        self.open_interest.add(chain);
     }
 
-    publish_to_telegram(&all_chains).await
+    /// Put with the highest rate of return of each underlying, ordered by underlying.
+    fn best_puts(&self) -> Vec<&model::OptionStrikeCandle> {
+        self.best_puts.values().collect()
+    }
+}
+
+/// Formats the open interest concentrations as a message section, wrapping each line in
+/// `bold` markup, e.g. `("<b>", "</b>")` for Telegram HTML or `("**", "**")` for Markdown.
+fn format_oi_concentrations(
+    concentrations: &[model::OiConcentration],
+    bold: (&str, &str),
+) -> String {
+    let mut text = String::from("Open interest concentrations\n");
+    for c in concentrations {
+        text.push_str(&format!(
+            "{}{} {} {:.2}: {:.1}% of OI ({}){}\n",
+            bold.0,
+            c.symbol,
+            c.side,
+            c.strike,
+            c.oi_pct * 100.0,
+            c.open_interest,
+            bold.1
+        ));
+    }
+    text
+}
+
+/// Formats the best option of each symbol as a message section, wrapping each line in `bold`
+/// markup like `format_oi_concentrations`.
+fn format_best_options(chains: &[&model::OptionStrikeCandle], bold: (&str, &str)) -> String {
+    let mut text = String::from("Best option per symbol\n");
+    for c in chains {
+        text.push_str(&format!(
+            "{}{} {} {:.2} {}: {:.1}% return{}\n",
+            bold.0,
+            c.underlying,
+            c.side,
+            c.strike,
+            c.expiration,
+            c.rate_of_return * 100.0,
+            bold.1
+        ));
+    }
+    text
 }
 
-pub async fn publish_to_telegram(all_chains: &[model::OptionStrikeCandle]) -> model::Result<()> {
-    // Save all_chains to a csv file and upload it to dropbox
+/// Sends the chains as CSV, together with any alerts, to Telegram and, when configured, to
+/// Discord (`DISCORD_WEBHOOK_URL`) and Slack (`SLACK_WEBHOOK_URL`, plus `SLACK_TOKEN` and
+/// `SLACK_CHANNEL` for the file upload). The Telegram alert message lists the best put of each
+/// symbol unless `alerts.hide_best_options` is set.
+pub async fn publish_notifications(
+    all_chains: &[model::OptionStrikeCandle],
+    alerts: &AlertConfig,
+) -> model::Result<()> {
+    let mut digest = ChainDigest::new(alerts.iv_hv_alert);
+    for chain in all_chains {
+        digest.add(chain);
+    }
     let csv = model::option_chain_to_csv_vec(all_chains)?;
+    send_notifications(csv, &digest, alerts).await
+}
 
+// Sends the CSV of chains summarised by `digest` to every configured channel.
+async fn send_notifications(
+    csv: Vec<u8>,
+    digest: &ChainDigest,
+    alerts: &AlertConfig,
+) -> model::Result<()> {
     let now = Local::now();
     let formatted_date = now.format("%Y%m%d_%H%M").to_string();
-    let filename = format!("/{}.csv", formatted_date);
+    let filename = format!("{}.csv", formatted_date);
+
+    let concentrations = digest
+        .open_interest
+        .concentrations(alerts.oi_concentration_threshold);
+    for c in &concentrations {
+        log::warn!(
+            symbol = c.symbol.as_str(),
+            side:% = c.side,
+            strike = c.strike,
+            oi_pct = c.oi_pct,
+            open_interest = c.open_interest;
+            "Strike holds an unusual share of open interest"
+        );
+    }
+
+    let best_options = if alerts.hide_best_options {
+        Vec::new()
+    } else {
+        digest.best_puts()
+    };
+    let sections: Vec<String> = [
+        (!best_options.is_empty()).then(|| format_best_options(&best_options, ("<b>", "</b>"))),
+        (!concentrations.is_empty())
+            .then(|| format_oi_concentrations(&concentrations, ("<b>", "</b>"))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let telegram_message = (!sections.is_empty()).then(|| sections.join("\n"));
+    let template = env::var("TELEGRAM_MESSAGE_TEMPLATE")
+        .unwrap_or_else(|_| template::DEFAULT_TEMPLATE.to_string());
+    let caption = template::render_message(&template, &message_data(digest, &now));
+    let (telegram, discord, slack) = tokio::join!(
+        publish_to_telegram(&filename, csv.clone(), caption, telegram_message),
+        publish_to_discord(&filename, &csv, &concentrations),
+        publish_to_slack(&filename, &csv, digest, &concentrations),
+    );
+    telegram?;
+    discord?;
+    slack?;
+    Ok(())
+}
+
+// Sends the CSV and any concentration alert to Discord when DISCORD_WEBHOOK_URL is set.
+async fn publish_to_discord(
+    filename: &str,
+    csv: &[u8],
+    concentrations: &[model::OiConcentration],
+) -> model::Result<()> {
+    let Ok(webhook_url) = env::var("DISCORD_WEBHOOK_URL") else {
+        return Ok(());
+    };
+    discord::send_discord_file(&webhook_url, filename, csv).await?;
+    if !concentrations.is_empty() {
+        let message = format_oi_concentrations(concentrations, ("**", "**"));
+        discord::send_discord_message(&webhook_url, &message).await?;
+    }
+    log::info!("discord send ok");
+    Ok(())
+}
 
+// Sends a summary to SLACK_WEBHOOK_URL when it is set. With SLACK_TOKEN and SLACK_CHANNEL also
+// set, the CSV is uploaded to the channel and a link to it is posted to the webhook.
+async fn publish_to_slack(
+    filename: &str,
+    csv: &[u8],
+    digest: &ChainDigest,
+    concentrations: &[model::OiConcentration],
+) -> model::Result<()> {
+    let Ok(webhook_url) = env::var("SLACK_WEBHOOK_URL") else {
+        return Ok(());
+    };
+    let text = format!("Option chains {}", filename);
+    let mut blocks = vec![serde_json::json!({
+        "type": "section",
+        "text": {
+            "type": "mrkdwn",
+            "text": format!("```\n{}```", format_summary_table(digest)),
+        },
+    })];
+    if !concentrations.is_empty() {
+        blocks.push(serde_json::json!({
+            "type": "section",
+            "text": {
+                "type": "mrkdwn",
+                "text": format_oi_concentrations(concentrations, ("*", "*")),
+            },
+        }));
+    }
+    slack::send_slack_message(&webhook_url, &text, Some(blocks.into())).await?;
+    log::info!("slack send message ok");
+    if let (Ok(token), Ok(channel)) = (env::var("SLACK_TOKEN"), env::var("SLACK_CHANNEL")) {
+        slack::send_slack_file(&webhook_url, filename, csv, &token, &channel).await?;
+        log::info!("slack send file ok");
+    }
+    Ok(())
+}
+
+// Summarises the chains per underlying: strike count and best annualised rate of return.
+fn format_summary_table(digest: &ChainDigest) -> String {
+    let mut table = format!("{:<8} {:>7} {:>9}\n", "SYMBOL", "STRIKES", "BEST ROR");
+    for (symbol, (count, best)) in &digest.symbols {
+        table.push_str(&format!("{:<8} {:>7} {:>9.3}\n", symbol, count, best));
+    }
+    table
+}
+
+/// Splits a message into chunks of at most `max_len` characters, breaking after newlines so
+/// lines stay whole. Only a single line longer than `max_len` is cut. Concatenating the
+/// chunks gives back the message.
+pub fn chunk_message(text: &str, max_len: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut chunk_len = 0;
+    for line in text.split_inclusive('\n') {
+        let line_len = line.chars().count();
+        if chunk_len + line_len > max_len && !chunk.is_empty() {
+            chunks.push(std::mem::take(&mut chunk));
+            chunk_len = 0;
+        }
+        if line_len <= max_len {
+            chunk.push_str(line);
+            chunk_len += line_len;
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        for piece in chars.chunks(max_len.max(1)) {
+            if !chunk.is_empty() {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+            chunk = piece.iter().collect();
+            chunk_len = piece.len();
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+// Uploads the CSV to Dropbox and returns a shared link to it. Returns None when Dropbox is not
+// configured or the upload fails.
+async fn dropbox_csv_link(path: &str, csv: &[u8]) -> Option<String> {
+    if env::var("dropbox_token").is_err() {
+        return None;
+    }
+    let link = match dropbox::upload_to_dropbox(csv, path).await {
+        Ok(()) => dropbox::get_shared_link(path).await,
+        Err(err) => Err(err),
+    };
+    match link {
+        Ok(url) => Some(url),
+        Err(err) => {
+            log::warn!(path = path, err:% = err; "Could not share the CSV on Dropbox");
+            None
+        }
+    }
+}
+
+// Summarises the chains for the Telegram caption template.
+fn message_data(digest: &ChainDigest, now: &DateTime<Local>) -> template::MessageData {
+    let period = match &digest.expirations {
+        Some((first, last)) if first != last => format!("{} to {}", first, last),
+        Some((first, _)) => first.clone(),
+        None => String::new(),
+    };
+    template::MessageData {
+        date: now.format("%Y-%m-%d %H:%M").to_string(),
+        symbol_count: digest.symbols.len(),
+        top_ror: digest.top_ror,
+        avg_dte: if digest.count == 0 {
+            0.0
+        } else {
+            digest.dte_sum / digest.count as f64
+        },
+        period,
+    }
+}
+
+// Sends the CSV document with a caption and, when given, an HTML message to the Telegram chat.
+async fn publish_to_telegram(
+    filename: &str,
+    csv: Vec<u8>,
+    caption: String,
+    message: Option<String>,
+) -> model::Result<()> {
+    let filename = format!("/{}", filename);
+    // The alert message links to a copy of the CSV when Dropbox is configured.
+    let csv_link = match &message {
+        Some(_) => dropbox_csv_link(&filename, &csv).await,
+        None => None,
+    };
     let token = env::var("telegram_bot_token")?;
     let chat_id = env::var("telegram_chat_id")?
         .parse::<i64>()
@@ -125,7 +1085,7 @@ pub async fn publish_to_telegram(all_chains: &[model::OptionStrikeCandle]) -> mo
             chat_id: ChatId::IntType(chat_id),
             document: InputFile::FileBytes(filename, csv),
             thumb: None,
-            caption: None,
+            caption: Some(caption),
             parse_mode: None,
             caption_entities: None,
             disable_content_type_detection: None,
@@ -143,5 +1103,235 @@ pub async fn publish_to_telegram(all_chains: &[model::OptionStrikeCandle]) -> mo
             return Err(model::QuotesError::TelegramError(err));
         }
     }
+
+    let chunks = message.map_or_else(Vec::new, |message| {
+        chunk_message(&message, constants::TELEGRAM_MAX_MESSAGE_LEN)
+    });
+    let chunk_count = chunks.len();
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let mut message =
+            telegram_bot_api::methods::SendMessage::new(ChatId::IntType(chat_id), chunk);
+        message.parse_mode = Some("HTML".to_string());
+        if let (Some(url), true) = (&csv_link, i + 1 == chunk_count) {
+            let mut button = InlineKeyboardButton::new("\u{1F4CA} View CSV".to_string());
+            button.url = Some(url.clone());
+            message.reply_markup = Some(ReplyMarkup::InlineKeyboardMarkup(
+                InlineKeyboardMarkup::new(vec![vec![button]]),
+            ));
+        }
+        match bot.send_message(message).await {
+            Ok(_) => log::info!("telegram send message ok"),
+            Err(err) => {
+                log::error!("telegram send message failed: {:?}", err);
+                return Err(model::QuotesError::TelegramError(err));
+            }
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(
+        underlying: &str,
+        side: model::OptionChainSide,
+        rate_of_return: f64,
+    ) -> model::OptionStrikeCandle {
+        model::OptionStrikeCandle {
+            underlying: underlying.to_string(),
+            strike: 100.0,
+            underlying_price: 110.0,
+            vwap: 0.0,
+            side,
+            bid: 1.0,
+            mid: 1.1,
+            ask: 1.2,
+            bid_size: 10,
+            ask_size: 10,
+            last: 1.1,
+            expiration: "2026-11-20".to_string(),
+            updated: "2026-10-18".to_string(),
+            dte: 33,
+            volume: 100,
+            open_interest: 500,
+            rate_of_return,
+            liquidity_score: 0.0,
+            strike_from: 90.0,
+            strike_to: 100.0,
+            implied_volatility: 0.0,
+            delta: 0.0,
+            theta: 0.0,
+            vega: 0.0,
+            iv_hv_ratio: 0.0,
+            implied_move_pct: 0.0,
+            rolling_sharpe_252: 0.0,
+            atr_trend: 0.0,
+            roc: 0.0,
+            max_dd_duration_days: 0,
+            recovery_factor: 0.0,
+            signal: String::new(),
+            earnings_risk: false,
+            cagr: 0.0,
+        }
+    }
+
+    fn digest(chains: &[model::OptionStrikeCandle]) -> ChainDigest {
+        let mut digest = ChainDigest::new(None);
+        for chain in chains {
+            digest.add(chain);
+        }
+        digest
+    }
+
+    fn filter(min_dte: u32, max_dte: u32) -> OptionChainFilterConfig {
+        OptionChainFilterConfig {
+            expiry_dte: None,
+            min_dte,
+            max_dte,
+            min_liquidity_score: 0.0,
+            earnings_calendar: None,
+        }
+    }
+
+    #[test]
+    fn both_sides_are_parsed_puts_first() {
+        use model::OptionChainSide::{Call, Put};
+        assert_eq!(parse_sides("both"), Ok(vec![Put, Call]));
+        assert_eq!(parse_sides("Call"), Ok(vec![Call]));
+        assert!(parse_sides("straddle").is_err());
+    }
+
+    #[test]
+    fn chains_outside_the_dte_bounds_are_filtered_out() {
+        let chains = [0, 1, 5, 30, 90]
+            .into_iter()
+            .map(|dte| model::OptionStrikeCandle {
+                dte,
+                ..chain("AAPL", model::OptionChainSide::Put, 0.01)
+            })
+            .collect();
+        let kept = filter_option_chains(chains, &filter(3, 60), &HashMap::new());
+        let dtes: Vec<u32> = kept.iter().map(|c| c.dte).collect();
+        assert_eq!(dtes, vec![5, 30]);
+    }
+
+    #[test]
+    fn dte_bounds_are_inclusive() {
+        let chains = [1, 5, 30]
+            .into_iter()
+            .map(|dte| model::OptionStrikeCandle {
+                dte,
+                ..chain("AAPL", model::OptionChainSide::Put, 0.01)
+            })
+            .collect();
+        let kept = filter_option_chains(chains, &filter(1, 30), &HashMap::new());
+        assert_eq!(kept.len(), 3);
+    }
+
+    #[test]
+    fn long_message_is_chunked_within_the_telegram_limit() {
+        let line = "AAPL put 100.00 2026-11-20: 1.1% return\n";
+        let text: String = line.repeat(10_000 / line.len() + 1)[..10_000].to_string();
+        let chunks = chunk_message(&text, constants::TELEGRAM_MAX_MESSAGE_LEN);
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| c.chars().count() <= constants::TELEGRAM_MAX_MESSAGE_LEN));
+        assert!(chunks[..chunks.len() - 1].iter().all(|c| c.ends_with('\n')));
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn a_line_longer_than_the_limit_is_cut() {
+        let text = "x".repeat(10_000);
+        let chunks = chunk_message(&text, constants::TELEGRAM_MAX_MESSAGE_LEN);
+        let lens: Vec<usize> = chunks.iter().map(|c| c.chars().count()).collect();
+        assert_eq!(lens, vec![4096, 4096, 1808]);
+        assert_eq!(chunks.concat(), text);
+    }
+
+    #[test]
+    fn short_message_is_one_chunk() {
+        assert_eq!(chunk_message("hello\n", 4096), vec!["hello\n"]);
+        assert!(chunk_message("", 4096).is_empty());
+    }
+
+    #[test]
+    fn best_put_per_symbol_keeps_the_highest_return_put_of_each_symbol() {
+        let chains = [
+            chain("MSFT", model::OptionChainSide::Put, 0.02),
+            chain("AAPL", model::OptionChainSide::Put, 0.01),
+            chain("AAPL", model::OptionChainSide::Call, 0.09),
+            chain("AAPL", model::OptionChainSide::Put, 0.03),
+        ];
+        let digest = digest(&chains);
+        let best: Vec<(&str, f64)> = digest
+            .best_puts()
+            .iter()
+            .map(|c| (c.underlying.as_str(), c.rate_of_return))
+            .collect();
+        assert_eq!(best, vec![("AAPL", 0.03), ("MSFT", 0.02)]);
+    }
+
+    #[test]
+    fn best_options_section_lists_each_symbol() {
+        let chains = [chain("AAPL", model::OptionChainSide::Put, 0.03)];
+        let text = format_best_options(&digest(&chains).best_puts(), ("<b>", "</b>"));
+        assert!(text.starts_with("Best option per symbol\n"));
+        assert!(text.contains("<b>AAPL"));
+        assert!(text.contains("3.0% return</b>"));
+    }
+
+    #[test]
+    fn open_interest_is_summed_across_expirations_of_a_strike() {
+        let chains = [
+            chain("AAPL", model::OptionChainSide::Put, 0.01),
+            model::OptionStrikeCandle {
+                expiration: "2026-11-27".to_string(),
+                ..chain("AAPL", model::OptionChainSide::Put, 0.01)
+            },
+            model::OptionStrikeCandle {
+                strike: 90.0,
+                ..chain("AAPL", model::OptionChainSide::Put, 0.01)
+            },
+        ];
+        let concentrations = detect_oi_concentration(&chains, 0.5);
+        assert_eq!(concentrations.len(), 1);
+        assert_eq!(concentrations[0].strike, 100.0);
+        assert_eq!(concentrations[0].open_interest, 1000);
+    }
+
+    #[test]
+    fn digest_summarises_the_chains_for_the_caption() {
+        let chains = [
+            chain("MSFT", model::OptionChainSide::Put, 0.02),
+            model::OptionStrikeCandle {
+                expiration: "2026-12-18".to_string(),
+                dte: 61,
+                ..chain("AAPL", model::OptionChainSide::Call, 0.05)
+            },
+            chain("AAPL", model::OptionChainSide::Put, 0.01),
+        ];
+        let data = message_data(&digest(&chains), &Local::now());
+        assert_eq!(data.symbol_count, 2);
+        assert_eq!(data.top_ror, 0.05);
+        assert!((data.avg_dte - (33.0 + 61.0 + 33.0) / 3.0).abs() < 1e-9);
+        assert_eq!(data.period, "2026-11-20 to 2026-12-18");
+        let table = format_summary_table(&digest(&chains));
+        assert!(table.contains("AAPL           2     0.050"));
+    }
+
+    #[test]
+    fn nan_strike_does_not_panic() {
+        let chains = [
+            model::OptionStrikeCandle {
+                strike: f64::NAN,
+                ..chain("AAPL", model::OptionChainSide::Put, 0.01)
+            },
+            chain("AAPL", model::OptionChainSide::Put, 0.01),
+        ];
+        assert_eq!(detect_oi_concentration(&chains, 0.4).len(), 2);
+    }
+}
@@ -0,0 +1,245 @@
+use std::time::Duration;
+
+use chrono::Local;
+use rusqlite::Connection;
+
+use crate::{
+    atr,
+    config::Config,
+    constants,
+    marketdata::api_caller,
+    model, momentum,
+    option::{self, AlertConfig, OptionChainFilterConfig},
+    quotes, sharpe, store, vwap,
+};
+
+// Steps of the pipeline that can be skipped.
+#[derive(clap::Args, Debug, Clone)]
+pub struct PipelineSteps {
+    #[arg(long)]
+    pub skip_quotes: bool,
+    #[arg(long)]
+    pub skip_atr: bool,
+    #[arg(long)]
+    pub skip_sharpe: bool,
+    #[arg(long)]
+    pub skip_options: bool,
+    #[arg(long)]
+    pub skip_publish: bool,
+    // Same as --skip-quotes --skip-atr --skip-sharpe --skip-options.
+    #[arg(long)]
+    pub only_publish: bool,
+    // Option side to pull in the options step: call, put or both. Fully qualified for the same
+    // reason as PullOptionChain's --side.
+    #[arg(long, default_value = "put", value_parser = option::parse_sides)]
+    pub side: ::std::vec::Vec<model::OptionChainSide>,
+}
+
+impl PipelineSteps {
+    /// Folds --only-publish into the individual flags, logs the skipped steps and fails when
+    /// every step is skipped.
+    pub fn resolve(mut self) -> model::Result<Self> {
+        if self.only_publish {
+            self.skip_quotes = true;
+            self.skip_atr = true;
+            self.skip_sharpe = true;
+            self.skip_options = true;
+        }
+        let steps = [
+            ("quotes", self.skip_quotes),
+            ("atr", self.skip_atr),
+            ("sharpe", self.skip_sharpe),
+            ("options", self.skip_options),
+            ("publish", self.skip_publish),
+        ];
+        for (step, skipped) in steps {
+            if skipped {
+                log::info!(step = step; "Skipping step");
+            }
+        }
+        if steps.iter().all(|(_, skipped)| *skipped) {
+            return Err(model::QuotesError::InvalidConfig(
+                "every PerformAll step is skipped".to_string(),
+            ));
+        }
+        Ok(self)
+    }
+}
+
+// When and how often WatchMode runs the pipeline.
+#[derive(clap::Args, Debug, Clone)]
+pub struct WatchSchedule {
+    /// Seconds between the starts of two runs.
+    #[arg(long, default_value_t = constants::WATCH_INTERVAL_SECS)]
+    pub interval_secs: u64,
+    /// Stop after this many runs, skipped runs included.
+    #[arg(long)]
+    pub max_runs: Option<u32>,
+    /// Run even when the market is closed.
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Runs the pipeline every `schedule.interval_secs`, starting immediately, until Ctrl-C is
+/// pressed or `schedule.max_runs` runs have happened. Runs are skipped while the market is
+/// closed unless `schedule.force` is set. A run in progress finishes before shutting down.
+pub async fn watch(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+    alerts: &AlertConfig,
+    filter: &OptionChainFilterConfig,
+    steps: &PipelineSteps,
+    schedule: &WatchSchedule,
+) {
+    let period = Duration::from_secs(schedule.interval_secs.max(1));
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // Created once so a Ctrl-C pressed during a run is not missed.
+    let mut shutdown = std::pin::pin!(tokio::signal::ctrl_c());
+
+    let mut runs = 0;
+    loop {
+        // Biased so a pending Ctrl-C wins over a tick that is ready at the same time.
+        tokio::select! {
+            biased;
+            _ = &mut shutdown => {
+                log::info!("Received Ctrl-C, stopping watch mode");
+                break;
+            }
+            _ = interval.tick() => {}
+        }
+
+        // The status is checked on every run; the quotes step's own check is cached per process.
+        if schedule.force || market_open().await {
+            run_pipeline(symbols_file_path, conn, config, alerts, filter, steps, true).await;
+        } else {
+            log::info!("Market is closed, skipping run");
+        }
+
+        runs += 1;
+        if schedule.max_runs.is_some_and(|max_runs| runs >= max_runs) {
+            log::info!(runs = runs; "Reached the maximum number of runs");
+            break;
+        }
+        let next_run = Local::now() + chrono::Duration::from_std(period).unwrap_or_default();
+        log::info!("Next run at {}", next_run.format("%Y-%m-%d %H:%M:%S"));
+    }
+}
+
+// Asks the API whether the market is open, treating errors as closed.
+async fn market_open() -> bool {
+    match api_caller::market_status().await {
+        Ok(status) => status == model::MarketStatus::Open,
+        Err(err) => {
+            log::error!("Error getting market status: {}", err);
+            false
+        }
+    }
+}
+
+/// Runs the steps of PerformAll that are not skipped: pull quotes, calculate ATR, VWAP,
+/// momentum and rolling Sharpe, pull the `steps.side` option chains and publish them, then
+/// checkpoint the database.
+/// A failing step is logged and the remaining steps still run.
+pub async fn run_pipeline(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+    alerts: &AlertConfig,
+    filter: &OptionChainFilterConfig,
+    steps: &PipelineSteps,
+    force: bool, // Pull quotes even when the market is closed.
+) {
+    if !steps.skip_quotes {
+        // Enough candles are pulled for the rolling Sharpe step to have a full window.
+        let pull_config = Config {
+            candle_count: config
+                .candle_count
+                .max(constants::ROLLING_SHARPE_WINDOW as u32),
+            ..*config
+        };
+        match quotes::pull_and_save(
+            symbols_file_path,
+            conn,
+            &pull_config,
+            force,
+            model::Period::Daily,
+            None,
+        )
+        .await
+        {
+            Ok(_) => log::info!("Successfully pulled and saved quotes"),
+            Err(err) => log::error!("Error pulling and saving quotes: {}", err),
+        }
+    }
+    if !steps.skip_atr {
+        match atr::calculate_and_save(
+            symbols_file_path,
+            conn,
+            atr::AtrMode::Ratio,
+            config,
+            false,
+            false,
+        ) {
+            Ok(_) => log::info!("Successfully calculated ATR and saved to DB"),
+            Err(err) => log::error!("Error calculating ATR: {}", err),
+        }
+        // The VWAP and momentum are reference values in the published chains.
+        match vwap::calculate_and_save(symbols_file_path, conn, constants::VWAP_WINDOW, config) {
+            Ok(_) => log::info!("Successfully calculated VWAP and saved to DB"),
+            Err(err) => log::error!("Error calculating VWAP: {}", err),
+        }
+        match momentum::calculate_and_save(
+            symbols_file_path,
+            conn,
+            constants::MOMENTUM_PERIOD,
+            config,
+        ) {
+            Ok(_) => log::info!("Successfully calculated momentum and saved to DB"),
+            Err(err) => log::error!("Error calculating momentum: {}", err),
+        }
+    }
+    if !steps.skip_sharpe {
+        let sharpe_config = model::SharpeConfig::builder()
+            .risk_free_rate(config.risk_free_rate)
+            .min_candles(constants::ROLLING_SHARPE_WINDOW)
+            .build();
+        match sharpe::calculate_and_save_rolling(
+            symbols_file_path,
+            conn,
+            constants::ROLLING_SHARPE_WINDOW,
+            &sharpe_config,
+        ) {
+            Ok(_) => log::info!("Successfully calculated rolling Sharpe and saved to DB"),
+            Err(err) => log::error!("Error calculating rolling Sharpe: {}", err),
+        }
+    }
+    if !steps.skip_options {
+        match option::retrieve_option_chains_base_on_ranges(
+            symbols_file_path,
+            &steps.side,
+            conn,
+            config,
+            alerts,
+            filter,
+            !steps.skip_publish,
+        )
+        .await
+        {
+            Ok(_) => log::info!("Successfully pulled and saved option chains"),
+            Err(err) => log::error!("Error pulling option chains: {}", err),
+        }
+    } else if !steps.skip_publish {
+        match option::publish_option_chains(symbols_file_path, conn, config, alerts, None, None)
+            .await
+        {
+            Ok(_) => log::info!("Successfully published option chains"),
+            Err(err) => log::error!("Error publishing option chains: {}", err),
+        }
+    }
+    // Keep the WAL file from growing across runs.
+    if let Err(err) = store::sqlite::run_checkpoint(conn, "PASSIVE") {
+        log::error!("Error checkpointing database: {}", err);
+    }
+}
@@ -0,0 +1,83 @@
+use chrono::DateTime;
+
+use crate::model;
+
+/// Renders the EMA ATR values as an ASCII line chart `height` rows tall and at most `width`
+/// columns wide, with the value range on the y-axis and the first and last dates on the
+/// x-axis. When there are more values than columns, only the latest `width` are plotted.
+pub fn plot_atr_history_ascii(
+    true_ranges: &[model::TrueRange], // Sorted from oldest to newest.
+    width: usize,
+    height: usize,
+) -> String {
+    if true_ranges.is_empty() || width == 0 || height == 0 {
+        return String::new();
+    }
+    let true_ranges = &true_ranges[true_ranges.len().saturating_sub(width)..];
+
+    let min = true_ranges
+        .iter()
+        .map(|tr| tr.ema_range)
+        .fold(f64::INFINITY, f64::min);
+    let max = true_ranges
+        .iter()
+        .map(|tr| tr.ema_range)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let span = max - min;
+
+    // Row 0 is the top of the chart.
+    let rows: Vec<usize> = true_ranges
+        .iter()
+        .map(|tr| {
+            if span == 0.0 {
+                return height / 2;
+            }
+            let level = ((tr.ema_range - min) / span * (height - 1) as f64).round() as usize;
+            height - 1 - level
+        })
+        .collect();
+
+    let labels = [format!("{:.4}", max), format!("{:.4}", min)];
+    let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+
+    let mut chart = String::new();
+    for row in 0..height {
+        let label = match row {
+            0 => labels[0].as_str(),
+            r if r == height - 1 => labels[1].as_str(),
+            _ => "",
+        };
+        let line: String = rows
+            .iter()
+            .map(|&r| if r == row { '*' } else { ' ' })
+            .collect();
+        chart.push_str(&format!("{:>label_width$} |{}\n", label, line.trim_end()));
+    }
+    chart.push_str(&format!(
+        "{:>label_width$} +{}\n",
+        "",
+        "-".repeat(true_ranges.len())
+    ));
+
+    let first = format_date(true_ranges[0].timestamp);
+    let last = format_date(true_ranges[true_ranges.len() - 1].timestamp);
+    let gap = true_ranges
+        .len()
+        .saturating_sub(first.len() + last.len())
+        .max(1);
+    chart.push_str(&format!(
+        "{:>label_width$}  {}{}{}",
+        "",
+        first,
+        " ".repeat(gap),
+        last
+    ));
+    chart
+}
+
+// Formats a Unix timestamp as a UTC date.
+fn format_date(timestamp: u32) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
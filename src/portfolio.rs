@@ -0,0 +1,210 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    store::{candle, option_chain, risk_metrics, rolling_sharpe},
+};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// A row of the positions file.
+#[derive(Debug, Deserialize)]
+struct PositionRow {
+    underlying: String,
+    side: String, // "call" or "put".
+    strike: f64,
+    expiration: String, // Expiration date as "%Y-%m-%d".
+    contracts: i32,     // Negative when sold.
+    entry_mid: f64,
+}
+
+// A row of the CSV export.
+#[derive(Debug, Serialize)]
+struct PositionSummaryRow<'a> {
+    underlying: &'a str,
+    side: &'a model::OptionChainSide,
+    strike: f64,
+    expiration: &'a str,
+    contracts: i32,
+    entry_mid: f64,
+    mid: f64,
+    delta: f64,
+    theta: f64,
+    vega: f64,
+    pnl: f64,
+}
+
+/// Output format of the portfolio summary.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum SummaryFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+/// Reads the positions file, matches each position against the latest stored option chain of
+/// its underlying and prints the portfolio summary to stdout.
+pub fn print_summary(
+    positions_file: &str, // CSV with underlying, side, strike, expiration, contracts, entry_mid.
+    conn: &mut Connection, // Database connection.
+    format: SummaryFormat,
+    config: &Config,
+) -> model::Result<()> {
+    option_chain::create_table(conn)?;
+    candle::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+
+    let mut reader = csv::Reader::from_path(positions_file)?;
+    let rows: Vec<PositionRow> = reader.deserialize().collect::<Result<_, _>>()?;
+
+    let mut chains: HashMap<String, Vec<model::OptionStrikeCandle>> = HashMap::new();
+    let mut positions = Vec::with_capacity(rows.len());
+    for row in rows {
+        if !chains.contains_key(&row.underlying) {
+            let chain = option_chain::retrieve_option_chain(conn, &row.underlying, None)
+                .with_symbol(&row.underlying)?;
+            chains.insert(row.underlying.clone(), chain);
+        }
+        let chain = chains.get_mut(&row.underlying).unwrap();
+        let found = chain.iter().position(|c| {
+            c.side.to_string() == row.side.to_lowercase()
+                && c.strike == row.strike
+                && c.expiration == row.expiration
+        });
+        let Some(index) = found else {
+            log::warn!(
+                symbol = row.underlying.as_str(), strike = row.strike, expiration = row.expiration.as_str();
+                "Position not found in the latest option chain, skipping"
+            );
+            continue;
+        };
+        positions.push(model::PortfolioPosition {
+            candle: chain.swap_remove(index),
+            contracts: row.contracts,
+            entry_mid: row.entry_mid,
+        });
+    }
+
+    let mut portfolio = build_portfolio(positions);
+    for underlying in chains.keys() {
+        let risk = risk_metrics::get_risk_metrics(
+            conn,
+            underlying,
+            config.candle_count,
+            config.risk_free_rate,
+        )
+        .with_symbol(underlying)?;
+        portfolio.risk_metrics.push(risk);
+    }
+    portfolio
+        .risk_metrics
+        .sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    match format {
+        SummaryFormat::Text => print_text(&portfolio),
+        SummaryFormat::Json => {
+            serde_json::to_writer_pretty(std::io::stdout(), &portfolio)?;
+            println!();
+        }
+        SummaryFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(std::io::stdout());
+            for position in &portfolio.positions {
+                writer.serialize(summary_row(position))?;
+            }
+            writer.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Sums the Greeks, entry premium and unrealized P&L of the positions.
+pub fn build_portfolio(positions: Vec<model::PortfolioPosition>) -> model::Portfolio {
+    let mut portfolio = model::Portfolio {
+        positions: Vec::new(),
+        net_delta: 0.0,
+        net_theta: 0.0,
+        net_vega: 0.0,
+        total_premium: 0.0,
+        unrealized_pnl: 0.0,
+        risk_metrics: Vec::new(),
+    };
+    for position in &positions {
+        let shares = position.contracts as f64 * constants::CONTRACT_MULTIPLIER;
+        portfolio.net_delta += position.candle.delta * shares;
+        portfolio.net_theta += position.candle.theta * shares;
+        portfolio.net_vega += position.candle.vega * shares;
+        portfolio.total_premium -= position.entry_mid * shares;
+        portfolio.unrealized_pnl += position_pnl(position);
+    }
+    portfolio.positions = positions;
+    portfolio
+}
+
+// Gain or loss of a position at the latest mid price.
+fn position_pnl(position: &model::PortfolioPosition) -> f64 {
+    (position.candle.mid - position.entry_mid)
+        * position.contracts as f64
+        * constants::CONTRACT_MULTIPLIER
+}
+
+fn summary_row(position: &model::PortfolioPosition) -> PositionSummaryRow<'_> {
+    PositionSummaryRow {
+        underlying: &position.candle.underlying,
+        side: &position.candle.side,
+        strike: position.candle.strike,
+        expiration: &position.candle.expiration,
+        contracts: position.contracts,
+        entry_mid: position.entry_mid,
+        mid: position.candle.mid,
+        delta: position.candle.delta,
+        theta: position.candle.theta,
+        vega: position.candle.vega,
+        pnl: position_pnl(position),
+    }
+}
+
+fn print_text(portfolio: &model::Portfolio) {
+    println!(
+        "{:<8} {:<5} {:>9} {:<11} {:>9} {:>9} {:>9} {:>10}",
+        "SYMBOL", "SIDE", "STRIKE", "EXPIRATION", "CONTRACTS", "ENTRY", "MID", "P&L"
+    );
+    for position in &portfolio.positions {
+        let row = summary_row(position);
+        println!(
+            "{:<8} {:<5} {:>9.2} {:<11} {:>9} {:>9.2} {:>9.2} {:>10.2}",
+            row.underlying,
+            row.side,
+            row.strike,
+            row.expiration,
+            row.contracts,
+            row.entry_mid,
+            row.mid,
+            row.pnl
+        );
+    }
+    println!();
+    println!("Net delta:      {:.2}", portfolio.net_delta);
+    println!("Net theta:      {:.2}", portfolio.net_theta);
+    println!("Net vega:       {:.2}", portfolio.net_vega);
+    println!("Total premium:  {:.2}", portfolio.total_premium);
+    println!("Unrealized P&L: {:.2}", portfolio.unrealized_pnl);
+    println!();
+    println!(
+        "{:<8} {:>8} {:>8} {:>8}",
+        "SYMBOL", "SHARPE", "MAX DD", "HV 30"
+    );
+    for risk in &portfolio.risk_metrics {
+        println!(
+            "{:<8} {:>8} {:>7.1}% {:>8}",
+            risk.symbol,
+            format_optional(risk.sharpe),
+            risk.max_drawdown * 100.0,
+            format_optional(risk.hv_30)
+        );
+    }
+}
+
+// Formats a metric to two decimals, or "-" when it is unknown.
+fn format_optional(value: Option<f64>) -> String {
+    value.map_or_else(|| "-".to_string(), |v| format!("{:.2}", v))
+}
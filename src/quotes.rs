@@ -1,35 +1,215 @@
-use crate::{constants, model, symbols};
+use crate::{
+    config::Config,
+    metrics,
+    model::{self, WithSymbol},
+    symbols,
+};
 use crate::{marketdata::api_caller, store};
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 use rusqlite::Connection;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+// A row of a candle CSV file.
+#[derive(Debug, Deserialize)]
+struct CandleRow {
+    symbol: String,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+    volume: u32,
+    timestamp: i64,
+}
+
+// Market status fetched once per run.
+static MARKET_STATUS: OnceLock<model::MarketStatus> = OnceLock::new();
+
+/// Returns the market status, calling the API only on the first use in a run.
+async fn market_status() -> model::Result<model::MarketStatus> {
+    if let Some(status) = MARKET_STATUS.get() {
+        return Ok(*status);
+    }
+    let status = api_caller::market_status().await?;
+    Ok(*MARKET_STATUS.get_or_init(|| status))
+}
 
 /// Pulls stock quotes for a list of symbols and saves them to the database.
+/// Daily candles go to the candle table; other periods go to their own table.
+/// Does nothing when the market is closed, unless `force` is set. With `since`, only the
+/// candles after that date, or after the latest stored daily candle when more recent, are pulled.
 pub async fn pull_and_save(
-    symbols_file_path: &str,   // Path to the file containing symbols.
-    conn: &mut Connection, // Database connection.
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+    force: bool,              // Pull even when the market is closed.
+    period: model::Period,    // Candle resolution.
+    since: Option<NaiveDate>, // Pull candles after this date instead of `candle_count`.
 ) -> model::Result<()> {
+    if !force {
+        match market_status().await {
+            Ok(model::MarketStatus::Closed) => {
+                log::info!("Market is closed, skipping candle pull (use --force to override)");
+                return Ok(());
+            }
+            Ok(_) => {}
+            Err(err) => log::warn!(err:% = err; "Could not fetch market status, pulling anyway"),
+        }
+    }
+
     let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
 
     // Initialize the candle table in the database.
-    store::candle::create_table(conn)?;
+    let table = store::candle::table_for_period(period);
+    store::candle::create_table_named(conn, table)?;
 
+    // The gauge counts the symbols of this run only, not of earlier WatchMode iterations.
+    metrics::SYMBOLS_PROCESSED.set(0);
+    let mut summary = model::RunSummary::default();
     for symbol in symbols.iter().filter(|s| !s.trim().is_empty()) {
+        let count = match since {
+            Some(since) => {
+                let mut from = since.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+                // Candles already stored need not be pulled again.
+                if period == model::Period::Daily {
+                    match store::candle::get_latest_candle(conn, symbol) {
+                        Ok(latest) => from = from.max(latest.timestamp as i64),
+                        Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                        Err(err) => return Err(err).with_symbol(symbol),
+                    }
+                }
+                let count = candles_since(from, Local::now().timestamp(), period);
+                log::info!(symbol = symbol.as_str(), count = count; "Pulling candles since {}", since);
+                count
+            }
+            None => config.candle_count,
+        };
         // Fetch candle data for the current symbol.
-        let candles =
-            api_caller::stock_candle(symbol, &Local::now(), constants::CANDLE_COUNT).await;
+        let candles = match period {
+            model::Period::Weekly => api_caller::weekly_candle(symbol, &Local::now(), count).await,
+            _ => api_caller::stock_candle(symbol, &Local::now(), count, period).await,
+        };
         // Handle the result of the candle data fetch.
         match candles {
             Ok(candles) => {
                 // Save the fetched candles to the database.
-                store::candle::save_candles(conn, &candles)?;
-                log::info!("Successfully fetched and saved candles for {}", symbol);
+                store::candle::save_candles_to(conn, table, &candles).await?;
+                metrics::SYMBOLS_PROCESSED.inc();
+                log::info!(symbol = symbol.as_str(); "Successfully fetched and saved candles");
+                summary.success(symbol);
             }
             Err(e) => {
-                log::error!("Failed to fetch and save candles for {}: {}", symbol, e);
-                return Err(model::QuotesError::HttpError(e));
+                log::error!(symbol = symbol.as_str(), err:% = e; "Failed to fetch and save candles");
+                summary.failure(symbol, model::QuotesError::from(e));
             }
         }
     }
+    metrics::update_db_candle_count(conn);
 
+    summary.finish()
+}
+
+// Upper bound on the number of candles of a period between two timestamps: every calendar day
+// is counted as a trading day, plus one for the candle in progress.
+fn candles_since(from_ts: i64, to_ts: i64, period: model::Period) -> u32 {
+    let days = ((to_ts - from_ts).max(0) / 86_400) as u32;
+    match period {
+        model::Period::Daily => days + 1,
+        model::Period::Weekly => days / 7 + 1,
+        model::Period::Monthly => days / 28 + 1,
+    }
+}
+
+/// Loads candles from a CSV file with columns `symbol,open,high,low,close,volume,timestamp`.
+/// Rows that cannot be parsed or whose prices are inconsistent are skipped. When `symbol` is
+/// given, only its rows are imported.
+pub async fn import_from_csv(
+    csv_path: &str,        // Path to the CSV file.
+    symbol: Option<&str>,  // Only import rows of this symbol.
+    conn: &mut Connection, // Database connection.
+) -> model::Result<()> {
+    store::candle::create_table(conn)?;
+
+    let mut reader = csv::Reader::from_path(csv_path)?;
+    let mut candles = Vec::new();
+    let mut skipped = 0;
+    for (i, row) in reader.deserialize::<CandleRow>().enumerate() {
+        let line = i + 2; // The header is line 1.
+        let row = match row {
+            Ok(row) => row,
+            Err(err) => {
+                log::warn!(line = line, err:% = err; "Skipping unreadable candle row");
+                skipped += 1;
+                continue;
+            }
+        };
+        if symbol.is_some_and(|symbol| symbol != row.symbol) {
+            continue;
+        }
+        if row.timestamp <= 0 || row.timestamp > u32::MAX as i64 {
+            log::warn!(line = line, symbol = row.symbol.as_str(); "Skipping invalid candle row: timestamp out of range");
+            skipped += 1;
+            continue;
+        }
+        let candle = model::Candle {
+            symbol: row.symbol,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+            volume: row.volume,
+            timestamp: row.timestamp as u32,
+        };
+        if let Err(reason) = model::validate_candle(&candle) {
+            log::warn!(line = line, symbol = candle.symbol.as_str(); "Skipping invalid candle row: {}", reason);
+            skipped += 1;
+            continue;
+        }
+        candles.push(candle);
+    }
+
+    // Every imported candle adds a row unless it replaced one.
+    let before = store::candle::count_candles_in(conn, "candle")?;
+    store::candle::save_candles(conn, &candles).await?;
+    let added = store::candle::count_candles_in(conn, "candle")? - before;
+    let duplicates = candles.len() as i64 - added;
+    log::info!(
+        imported = candles.len(), skipped = skipped, replaced = duplicates;
+        "Imported candles from {}", csv_path
+    );
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn invalid_rows_are_skipped_and_duplicates_replaced() {
+        let mut conn = store::sqlite::test_conn();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            "symbol,open,high,low,close,volume,timestamp
+AAPL,10,11,9,10.5,100,100
+AAPL,10,10.2,9,10.5,100,200
+AAPL,10,11,9,10.5,100,0
+AAPL,-1,11,9,10.5,100,300
+AAPL,10,12,9,11,100,100
+MSFT,20,21,19,20.5,100,100
+"
+        )
+        .unwrap();
+
+        import_from_csv(file.path().to_str().unwrap(), Some("AAPL"), &mut conn)
+            .await
+            .unwrap();
+        // The high below the close, the zero timestamp and the negative open are skipped, and
+        // the second candle at 100 replaces the first.
+        let candles = store::candle::get_candles_sorted_asc(&conn, "AAPL", 10).unwrap();
+        assert_eq!(candles.len(), 1);
+        assert_eq!(candles[0].close, 11.0);
+        assert_eq!(store::candle::count_candles(&conn, "MSFT").unwrap(), 0);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    store::{candle, option_chain, rolling_sharpe, sortino},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Calculates the rolling Sharpe ratio of every symbol and saves the series to the database,
+/// along with the Sortino ratio of the latest window. Symbols with fewer than `window` or `sharpe_config.min_candles` candles are skipped. The run
+/// only fails, with a `PartialFailure` describing why each was skipped, when every symbol was.
+pub fn calculate_and_save_rolling(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    window: usize,           // Number of candles in each window.
+    sharpe_config: &model::SharpeConfig,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    rolling_sharpe::create_table(conn)?;
+    sortino::create_table(conn)?;
+
+    let mut entries = Vec::new();
+    let mut sortinos = Vec::new();
+    let mut summary = model::RunSummary::default();
+    for symbol in symbols {
+        // Checked on the aggregate before any candle is loaded.
+        let count = candle::get_candle_summary(conn, &symbol)
+            .with_symbol(&symbol)?
+            .count;
+        if (count as usize) < window.max(sharpe_config.min_candles) {
+            log::warn!(
+                symbol = symbol.as_str(), count = count, window = window;
+                "Not enough candles, skipping rolling Sharpe"
+            );
+            summary.failure(&symbol, format!("not enough candles ({})", count));
+            continue;
+        }
+
+        // Fetch enough candles to produce a series of `window` rolling values.
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, (window * 2) as u32)
+            .with_symbol(&symbol)?;
+        let sharpes =
+            match calculate_rolling_sharpe(&candles, window, sharpe_config.risk_free_rate()) {
+                Ok(sharpes) => sharpes,
+                Err(err) => {
+                    log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping rolling Sharpe");
+                    summary.failure(&symbol, err);
+                    continue;
+                }
+            };
+
+        log::info!(
+            symbol = symbol.as_str(), window = window, count = sharpes.len();
+            "Calculated rolling Sharpe values"
+        );
+        // Symbols without a losing day in the window have no Sortino ratio.
+        let latest = &candles[candles.len() - window..];
+        let daily_risk_free_rate =
+            sharpe_config.risk_free_rate() / constants::TRADING_DAYS_PER_YEAR;
+        let excess_returns: Vec<f64> = latest
+            .windows(2)
+            .map(|w| w[1].close / w[0].close - 1.0 - daily_risk_free_rate)
+            .collect();
+        if let Some(value) = sortino_ratio(&excess_returns, constants::TRADING_DAYS_PER_YEAR) {
+            sortinos.push((symbol.clone(), value, latest[window - 1].timestamp));
+        }
+        entries.extend(
+            sharpes
+                .into_iter()
+                .map(|(timestamp, sharpe)| (symbol.clone(), timestamp, sharpe)),
+        );
+        summary.success(&symbol);
+    }
+
+    // Every symbol's series is saved in one transaction, and so are the Sortino ratios.
+    rolling_sharpe::save_rolling_sharpes_batch(conn, window, &entries)?;
+    sortino::save_sortino_ratios_batch(conn, window, &sortinos)?;
+    summary.finish()
+}
+
+/// Calculates the annualised Sharpe ratio for each window-sized slice of the candles.
+/// Returns `(timestamp, sharpe)` pairs keyed by the timestamp of the last candle in the slice.
+pub fn calculate_rolling_sharpe(
+    candles: &[model::Candle],
+    window: usize,
+    risk_free_rate: f64,
+) -> model::Result<Vec<(u32, f64)>> {
+    if window < 3 || candles.len() < window {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            format!(
+                "Not enough candles for rolling Sharpe calculation (window: {}, candles: {})",
+                window,
+                candles.len()
+            ),
+        ));
+    }
+
+    let daily_risk_free_rate = risk_free_rate / constants::TRADING_DAYS_PER_YEAR;
+    Ok(candles
+        .windows(window)
+        .map(|slice| {
+            let excess_returns: Vec<f64> = slice
+                .windows(2)
+                .map(|w| w[1].close / w[0].close - 1.0 - daily_risk_free_rate)
+                .collect();
+            let timestamp = slice.last().unwrap().timestamp;
+            (
+                timestamp,
+                sharpe_ratio(&excess_returns, constants::TRADING_DAYS_PER_YEAR),
+            )
+        })
+        .collect())
+}
+
+/// Return on capital of each strike if it were sold fully cash-secured: the premium of one
+/// contract over the cash set aside to buy its shares at the strike, which per share is
+/// `mid / strike`. Strikes at 0 are skipped.
+pub fn calculate_returns_from_option_premium(chains: &[model::OptionStrikeCandle]) -> Vec<f64> {
+    chains
+        .iter()
+        .filter(|c| c.strike > 0.0)
+        .map(|c| c.mid / c.strike)
+        .collect()
+}
+
+/// Annualised Sharpe ratio of selling each of the strikes cash-secured. The risk-free return
+/// over each strike's days to expiration is subtracted from its premium return, and the
+/// ratio is annualised by the number of such trades that fit in a year at the average DTE.
+pub fn calculate_strategy_sharpe(
+    chains: &[model::OptionStrikeCandle],
+    risk_free_rate: f64,
+) -> model::Result<f64> {
+    let returns = calculate_returns_from_option_premium(chains);
+    if returns.len() < 3 {
+        return Err(model::QuotesError::not_enough_candles(
+            chains.first().map_or("", |c| c.underlying.as_str()),
+            format!(
+                "Not enough strikes for strategy Sharpe calculation (strikes: {})",
+                returns.len()
+            ),
+        ));
+    }
+
+    // Same filter as calculate_returns_from_option_premium, so the two line up.
+    let dtes: Vec<f64> = chains
+        .iter()
+        .filter(|c| c.strike > 0.0)
+        .map(|c| c.dte.max(1) as f64)
+        .collect();
+    let excess_returns: Vec<f64> = returns
+        .iter()
+        .zip(&dtes)
+        .map(|(r, dte)| r - risk_free_rate * dte / constants::DAYS_PER_YEAR)
+        .collect();
+    let average_dte = dtes.iter().sum::<f64>() / dtes.len() as f64;
+    Ok(sharpe_ratio(
+        &excess_returns,
+        constants::DAYS_PER_YEAR / average_dte,
+    ))
+}
+
+/// Prints the strategy Sharpe ratio of selling the puts in every stored option chain
+/// snapshot of each symbol.
+pub fn print_strategy_sharpes(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &Connection,       // Database connection.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    option_chain::create_table(conn)?;
+
+    println!("{:<8} {:>7} SHARPE", "SYMBOL", "STRIKES");
+    for symbol in symbols {
+        let mut puts = Vec::new();
+        for (run_id, updated) in
+            option_chain::list_snapshot_times(conn, &symbol).with_symbol(&symbol)?
+        {
+            let chain = option_chain::get_option_chain_at(conn, &symbol, &run_id, &updated)
+                .with_symbol(&symbol)?;
+            puts.extend(
+                chain
+                    .into_iter()
+                    .filter(|c| c.side == model::OptionChainSide::Put),
+            );
+        }
+        match calculate_strategy_sharpe(&puts, config.risk_free_rate) {
+            Ok(sharpe) => println!("{:<8} {:>7} {:.2}", symbol, puts.len(), sharpe),
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping strategy Sharpe")
+            }
+        }
+    }
+    Ok(())
+}
+
+// Annualised Sharpe ratio of excess returns measured `periods_per_year` times a year.
+// Returns 0.0 when the returns do not vary.
+fn sharpe_ratio(excess_returns: &[f64], periods_per_year: f64) -> f64 {
+    let n = excess_returns.len() as f64;
+    let mean = excess_returns.iter().sum::<f64>() / n;
+    let variance = excess_returns
+        .iter()
+        .map(|r| (r - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0);
+    let std_dev = variance.sqrt();
+    if std_dev == 0.0 {
+        return 0.0;
+    }
+    mean / std_dev * periods_per_year.sqrt()
+}
+
+/// Annualised Sortino ratio of excess returns measured `periods_per_year` times a year: the mean
+/// excess return over the downside deviation, which only counts returns below zero.
+/// Returns `None` when no return is below zero.
+pub fn sortino_ratio(excess_returns: &[f64], periods_per_year: f64) -> Option<f64> {
+    let n = excess_returns.len() as f64;
+    let mean = excess_returns.iter().sum::<f64>() / n;
+    let downside = excess_returns
+        .iter()
+        .map(|r| r.min(0.0).powi(2))
+        .sum::<f64>()
+        / n;
+    if downside == 0.0 {
+        return None;
+    }
+    Some(mean / downside.sqrt() * periods_per_year.sqrt())
+}
@@ -0,0 +1,121 @@
+use crate::{
+    config::Config,
+    constants,
+    model::{self, WithSymbol},
+    store::{candle, rolling_sharpe},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Bollinger bands as `(lower, middle, upper)`.
+pub type BollingerBands = (f64, f64, f64);
+
+/// Derives the put-selling signal of an option chain's underlying.
+pub fn generate_signal(
+    candle: &model::OptionStrikeCandle,
+    sharpe: f64,
+    rsi: f64,
+    bollinger: BollingerBands,
+    config: &Config,
+) -> model::Signal {
+    signal_for_close(candle.underlying_price, sharpe, rsi, bollinger, config)
+}
+
+// Applies the rule set to the latest close of the underlying.
+fn signal_for_close(
+    close: f64,
+    sharpe: f64,
+    rsi: f64,
+    bollinger: BollingerBands,
+    config: &Config,
+) -> model::Signal {
+    let (lower, _, _) = bollinger;
+    if rsi < config.signal_rsi_warning {
+        model::Signal::Warning(format!(
+            "RSI {:.1} below {}",
+            rsi, config.signal_rsi_warning
+        ))
+    } else if rsi < config.signal_rsi_sell && close > lower && sharpe > config.signal_min_sharpe {
+        model::Signal::Sell
+    } else {
+        model::Signal::Hold
+    }
+}
+
+/// Returns the RSI and Bollinger bands of the latest stored candles of a symbol,
+/// or `None` when there are not enough candles.
+pub fn indicators(conn: &Connection, symbol: &str) -> model::Result<Option<(f64, BollingerBands)>> {
+    let candles = candle::get_candles_sorted_asc(conn, symbol, constants::SIGNAL_CANDLE_COUNT)?;
+    let closes: Vec<f64> = candles.iter().map(|c| c.close).collect();
+    Ok(rsi(&closes, constants::RSI_PERIOD).zip(bollinger_bands(
+        &closes,
+        constants::BOLLINGER_PERIOD,
+        constants::BOLLINGER_STD_DEV,
+    )))
+}
+
+/// Prints the signal of every symbol, based on its latest close, RSI, Bollinger bands and
+/// latest 252-day rolling Sharpe ratio.
+pub fn print_signals(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &Connection,       // Database connection.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    candle::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+
+    println!("{:<8} SIGNAL", "SYMBOL");
+    for symbol in symbols {
+        let Some((rsi, bollinger)) = indicators(conn, &symbol).with_symbol(&symbol)? else {
+            log::warn!(symbol = symbol.as_str(); "Not enough candles, skipping signal");
+            continue;
+        };
+        let close = candle::get_latest_candle(conn, &symbol)
+            .with_symbol(&symbol)?
+            .close;
+        let sharpe = rolling_sharpe::get_latest_rolling_sharpe(
+            conn,
+            &symbol,
+            constants::ROLLING_SHARPE_WINDOW,
+        )
+        .with_symbol(&symbol)?
+        .unwrap_or(0.0);
+        let signal = signal_for_close(close, sharpe, rsi, bollinger, config);
+        println!("{:<8} {}", symbol, signal);
+    }
+    Ok(())
+}
+
+/// Relative strength index of the closes using Wilder's smoothing.
+/// Closes must be sorted from oldest to newest.
+pub fn rsi(closes: &[f64], period: usize) -> Option<f64> {
+    if period == 0 || closes.len() <= period {
+        return None;
+    }
+    let changes: Vec<f64> = closes.windows(2).map(|w| w[1] - w[0]).collect();
+    let (first, rest) = changes.split_at(period);
+    let mut avg_gain = first.iter().filter(|c| **c > 0.0).sum::<f64>() / period as f64;
+    let mut avg_loss = -first.iter().filter(|c| **c < 0.0).sum::<f64>() / period as f64;
+    for change in rest {
+        avg_gain = (avg_gain * (period - 1) as f64 + change.max(0.0)) / period as f64;
+        avg_loss = (avg_loss * (period - 1) as f64 + (-change).max(0.0)) / period as f64;
+    }
+    if avg_loss == 0.0 {
+        return Some(100.0);
+    }
+    Some(100.0 - 100.0 / (1.0 + avg_gain / avg_loss))
+}
+
+/// Bollinger bands `(lower, middle, upper)` over the last `period` closes.
+pub fn bollinger_bands(closes: &[f64], period: usize, std_devs: f64) -> Option<BollingerBands> {
+    if period == 0 || closes.len() < period {
+        return None;
+    }
+    let window = &closes[closes.len() - period..];
+    let mean = window.iter().sum::<f64>() / period as f64;
+    let variance = window.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / period as f64;
+    let band = variance.sqrt() * std_devs;
+    Some((mean - band, mean, mean + band))
+}
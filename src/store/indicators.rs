@@ -0,0 +1,37 @@
+use rusqlite::{params, Connection, Result};
+
+/// Initializes the indicators table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS indicators (
+            symbol TEXT NOT NULL,
+            indicator TEXT NOT NULL,
+            period INTEGER NOT NULL,
+            value REAL NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (symbol, indicator, period)
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, indicator, value, timestamp)` entries for a period.  Uses REPLACE to update existing entries.
+pub fn save_indicators(
+    conn: &mut Connection,
+    period: u32,
+    indicators: &[(String, String, f64, u32)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO indicators (symbol, indicator, period, value, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (symbol, indicator, value, timestamp) in indicators {
+            stmt.execute(params![symbol, indicator, period, value, timestamp])
+                .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
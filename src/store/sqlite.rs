@@ -1,19 +1,352 @@
-use rusqlite::{Connection, OpenFlags, Result};
+use super::super::model::{self, QuotesError};
+use rusqlite::{types::ValueRef, Connection, ErrorCode, OpenFlags, Result};
+use std::{path::Path, time::Duration};
+
+// Value of `sqlite_file` that selects a throwaway in-memory database.
+const IN_MEMORY: &str = ":memory:";
 
 /// Initializes a connection to the SQLite database.
 pub fn init_connection() -> Result<Connection> {
+    let path = std::env::var("sqlite_file").unwrap();
+    if path == IN_MEMORY {
+        return init_in_memory_connection();
+    }
+
     // Open the database file specified by the environment variable `sqlite_file`.
     // The database is opened in read-write mode, and it will be created if it doesn't exist.
     let conn = Connection::open_with_flags(
-        std::env::var("sqlite_file").unwrap(),
+        path,
         OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
     )?;
+    configure(&conn)?;
+    Ok(conn)
+}
 
+/// Opens an empty in-memory database with the same settings as `init_connection`.
+/// Nothing written to it outlives the connection.
+pub fn init_in_memory_connection() -> Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    configure(&conn)?;
+    Ok(conn)
+}
+
+/// Opens an empty in-memory database for tests.
+#[cfg(test)]
+pub fn test_conn() -> Connection {
+    init_in_memory_connection().unwrap()
+}
+
+// Applies the connection settings shared by file and in-memory databases.
+fn configure(conn: &Connection) -> Result<()> {
     // Set the journal mode to WAL (Write-Ahead Logging) for better concurrency.
+    // In-memory databases keep their "memory" journal mode.
     conn.query_row("PRAGMA journal_mode=WAL;", [], |_row| Ok(()))?;
 
     // Set the synchronous mode to NORMAL for better performance.  This trades some durability for speed.
     conn.execute("PRAGMA synchronous=NORMAL;", [])?;
 
-    Ok(conn)
+    Ok(())
+}
+
+/// Runs `f`, retrying up to `max_retries` times with `delay_ms` between attempts while the
+/// database is busy, as when another process holds a lock on a WAL database. The delay is
+/// awaited so the runtime's worker thread is not blocked.
+pub async fn execute_with_retry<T, F: FnMut() -> Result<T>>(
+    mut f: F,
+    max_retries: u32,
+    delay_ms: u64,
+) -> Result<T> {
+    let mut attempt = 0;
+    loop {
+        match f() {
+            Err(err)
+                if attempt < max_retries
+                    && err.sqlite_error_code() == Some(ErrorCode::DatabaseBusy) =>
+            {
+                attempt += 1;
+                log::warn!(attempt = attempt, max_retries = max_retries; "Database is busy, retrying");
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Tables every full run is expected to create.
+pub const REQUIRED_TABLES: [&str; 6] = [
+    "candle",
+    "true_range",
+    "option_strike",
+    "rolling_sharpe",
+    "streaks",
+    "recovery_factor",
+];
+
+/// Runs SQLite's integrity check, counts the rows of each required table and looks for
+/// symbols with missing derived data. Returns one warning per problem found.
+pub fn check_integrity(conn: &Connection) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let results: Vec<String> = stmt
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    warnings.extend(
+        results
+            .into_iter()
+            .filter(|r| r != "ok")
+            .map(|r| format!("Integrity check: {}", r)),
+    );
+
+    let mut missing_tables = Vec::new();
+    for table in REQUIRED_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            [table],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            warnings.push(format!("Table {} does not exist", table));
+            missing_tables.push(table);
+            continue;
+        }
+        let count: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })?;
+        log::info!(table = table, rows = count; "Row count");
+    }
+
+    // Symbols present in one table but missing from another.
+    let checks = [
+        ("candle", "symbol", "true_range", "symbol"),
+        ("candle", "symbol", "rolling_sharpe", "symbol"),
+        ("option_strike", "underlying", "candle", "symbol"),
+    ];
+    for (from, from_column, to, to_column) in checks {
+        if missing_tables.contains(&from) || missing_tables.contains(&to) {
+            continue;
+        }
+        let mut stmt = conn.prepare(&format!(
+            "SELECT DISTINCT {from_column} FROM {from}
+             WHERE {from_column} NOT IN (SELECT {to_column} FROM {to})
+             ORDER BY {from_column}"
+        ))?;
+        let symbols: Vec<String> = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<_>>()?;
+        for symbol in symbols {
+            warnings.push(format!(
+                "{} is in {} but has no rows in {}",
+                symbol, from, to
+            ));
+        }
+    }
+
+    Ok(warnings)
+}
+
+/// Names of the tables in the database, sorted, without SQLite's internal tables.
+pub fn get_table_names(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'
+         ORDER BY name",
+    )?;
+    let rows = stmt.query_map([], |row| row.get(0))?;
+    rows.collect()
+}
+
+/// Counts the rows and estimates the size of every table. Sizes come from the `dbstat`
+/// virtual table when SQLite provides it, otherwise from the summed length of the values.
+pub fn get_database_stats(conn: &Connection) -> Result<model::DbStats> {
+    let names = get_table_names(conn)?;
+
+    let mut tables = Vec::with_capacity(names.len());
+    for name in names {
+        let row_count: i64 =
+            conn.query_row(&format!("SELECT COUNT(*) FROM \"{}\"", name), [], |row| {
+                row.get(0)
+            })?;
+        let size_bytes = match conn.query_row(
+            "SELECT COALESCE(SUM(pgsize), 0) FROM dbstat WHERE name = ?1",
+            [&name],
+            |row| row.get::<_, i64>(0),
+        ) {
+            Ok(size) => size,
+            Err(_) => values_length(conn, &name)?,
+        };
+        tables.push(model::TableStats {
+            name,
+            row_count: row_count as u64,
+            size_bytes: size_bytes as u64,
+        });
+    }
+    Ok(model::DbStats { tables })
+}
+
+// Sums the length of every value of a table.
+fn values_length(conn: &Connection, table: &str) -> Result<i64> {
+    let mut stmt = conn.prepare("SELECT name FROM pragma_table_info(?1)")?;
+    let columns: Vec<String> = stmt
+        .query_map([table], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    if columns.is_empty() {
+        return Ok(0);
+    }
+    let lengths: Vec<String> = columns
+        .iter()
+        .map(|column| format!("COALESCE(length(\"{}\"), 0)", column))
+        .collect();
+    conn.query_row(
+        &format!(
+            "SELECT COALESCE(SUM({}), 0) FROM \"{}\"",
+            lengths.join(" + "),
+            table
+        ),
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Writes every row of a table to a CSV file with a header row. Returns the number of rows.
+pub fn export_table_to_csv(
+    conn: &Connection,
+    table_name: &str,
+    output_path: &Path,
+) -> model::Result<u64> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\"", table_name))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let mut writer = csv::Writer::from_path(output_path)?;
+    writer.write_record(&columns)?;
+
+    let mut rows = stmt.query([])?;
+    let mut count = 0;
+    while let Some(row) = rows.next()? {
+        let mut record = Vec::with_capacity(columns.len());
+        for i in 0..columns.len() {
+            record.push(match row.get_ref(i)? {
+                ValueRef::Null => String::new(),
+                ValueRef::Integer(v) => v.to_string(),
+                ValueRef::Real(v) => v.to_string(),
+                ValueRef::Text(v) | ValueRef::Blob(v) => String::from_utf8_lossy(v).into_owned(),
+            });
+        }
+        writer.write_record(&record)?;
+        count += 1;
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// Tables that may be cleared, including the optional weekly and monthly candle tables.
+const CLEARABLE_TABLES: [&str; 17] = [
+    "candle",
+    "candle_weekly",
+    "candle_monthly",
+    "true_range",
+    "true_range_absolute",
+    "option_strike",
+    "rolling_sharpe",
+    "sortino",
+    "streaks",
+    "recovery_factor",
+    "vwap",
+    "momentum",
+    "max_drop",
+    "max_drop_rolling",
+    "implied_move",
+    "indicators",
+    "cagr",
+];
+
+/// Deletes every row of a known table. Returns the number of deleted rows.
+pub fn clear_table(conn: &Connection, table_name: &str) -> model::Result<u64> {
+    // Only whitelisted names are interpolated into the statement.
+    if !CLEARABLE_TABLES.contains(&table_name) {
+        return Err(QuotesError::InvalidConfig(format!(
+            "unknown table {}, expected one of {}",
+            table_name,
+            CLEARABLE_TABLES.join(", ")
+        )));
+    }
+    let deleted = conn.execute(&format!("DELETE FROM {}", table_name), [])?;
+    log::warn!(table = table_name, rows = deleted; "Cleared table");
+    Ok(deleted as u64)
+}
+
+/// Modes accepted by `PRAGMA wal_checkpoint`.
+pub const CHECKPOINT_MODES: [&str; 4] = ["PASSIVE", "FULL", "RESTART", "TRUNCATE"];
+
+/// Copies the WAL file back into the database with `PRAGMA wal_checkpoint(mode)` and logs
+/// the result. Returns `(busy, log, checkpointed)`: whether the checkpoint was blocked, the
+/// number of pages in the WAL file and the number of pages written back.
+pub fn run_checkpoint(conn: &Connection, mode: &str) -> model::Result<(i32, i32, i32)> {
+    let mode = mode.to_uppercase();
+    // Only whitelisted modes are interpolated into the statement.
+    if !CHECKPOINT_MODES.contains(&mode.as_str()) {
+        return Err(QuotesError::InvalidConfig(format!(
+            "unknown checkpoint mode {}, expected one of {}",
+            mode,
+            CHECKPOINT_MODES.join(", ")
+        )));
+    }
+    let (busy, log, checkpointed) =
+        conn.query_row(&format!("PRAGMA wal_checkpoint({})", mode), [], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })?;
+    log::info!(
+        mode = mode.as_str(), busy = busy, log = log, checkpointed = checkpointed;
+        "WAL checkpoint"
+    );
+    Ok((busy, log, checkpointed))
+}
+
+/// Rebuilds the database file to reclaim the space of deleted rows.
+pub fn vacuum(conn: &Connection) -> Result<()> {
+    conn.execute("VACUUM", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn busy() -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None)
+    }
+
+    #[tokio::test]
+    async fn execute_with_retry_retries_while_busy() {
+        let mut calls = 0;
+        let result = execute_with_retry(
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err(busy())
+                } else {
+                    Ok(calls)
+                }
+            },
+            3,
+            1,
+        )
+        .await;
+        assert_eq!(result.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn execute_with_retry_gives_up_after_max_retries() {
+        let mut calls = 0;
+        let result: Result<()> = execute_with_retry(
+            || {
+                calls += 1;
+                Err(busy())
+            },
+            2,
+            1,
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls, 3);
+    }
 }
@@ -0,0 +1,44 @@
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// Initializes the streaks table in the SQLite database.
+pub fn create_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS streaks (
+            symbol TEXT NOT NULL,
+            max_dd_duration_days INTEGER NOT NULL,
+            timestamp INTEGER NOT NULL
+        );",
+        [],
+    )?;
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_streaks_symbol ON streaks (symbol);",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Saves `(symbol, max_dd_duration_days, timestamp)` entries.  Uses REPLACE to update existing entries.
+pub fn save_streaks(conn: &mut Connection, streaks: &[(String, u32, u32)]) -> Result<()> {
+    let transaction = conn.transaction()?;
+    {
+        let mut stmt = transaction.prepare(
+            "REPLACE INTO streaks (symbol, max_dd_duration_days, timestamp)
+             VALUES (?1, ?2, ?3)",
+        )?;
+        for (symbol, max_dd_duration_days, timestamp) in streaks {
+            stmt.execute(params![symbol, max_dd_duration_days, timestamp])
+                .err(); // Ignore errors during individual inserts; transaction will handle overall success/failure.
+        }
+    }
+    transaction.commit()
+}
+
+/// Retrieves the maximum drawdown duration in days of a symbol, if calculated.
+pub fn get_max_dd_duration_days(conn: &Connection, symbol: &str) -> Result<Option<u32>> {
+    conn.query_row(
+        "SELECT max_dd_duration_days FROM streaks WHERE symbol = ?1",
+        [symbol],
+        |row| row.get(0),
+    )
+    .optional()
+}
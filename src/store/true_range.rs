@@ -1,33 +1,50 @@
-use rusqlite::{params, Connection, Result};
+use std::collections::HashMap;
+
+use rusqlite::{params, params_from_iter, Connection, Result};
 
 use crate::model;
 
-/// Initializes the candle table in the SQLite database.
-pub fn create_table(conn: &Connection) -> Result<()> {
+// Ratio true ranges, the fractions of the price that option strike ranges are built from.
+pub const RATIO_TABLE: &str = "true_range";
+// Absolute true ranges, in price units.
+pub const ABSOLUTE_TABLE: &str = "true_range_absolute";
+
+/// Initializes a true range table in the SQLite database.
+pub fn create_table_named(conn: &Connection, table: &str) -> Result<()> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS true_range (
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
             symbol TEXT NOT NULL,
             percentile_range REAL NOT NULL,
             ema_range REAL NOT NULL,
             timestamp INTEGER NOT NULL
-        );",
+        );"
+        ),
         [],
     )?;
+    // Older databases kept a single row per symbol; keep the history instead.
+    conn.execute("DROP INDEX IF EXISTS idx_symbol;", [])?;
     conn.execute(
-        "CREATE UNIQUE INDEX IF NOT EXISTS idx_symbol ON true_range (symbol);",
+        &format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_{table}_symbol_timestamp ON {table} (symbol, timestamp);"
+        ),
         [],
     )?;
     Ok(())
 }
 
-/// Saves a vector of candles to the candle table.  Uses REPLACE to update existing entries.
-pub fn save_true_ranges(conn: &mut Connection, true_ranges: &[model::TrueRange]) -> Result<()> {
+/// Saves true ranges to a true range table.  Uses REPLACE to update existing entries.
+pub fn save_true_ranges_to(
+    conn: &mut Connection,
+    table: &str,
+    true_ranges: &[model::TrueRange],
+) -> Result<()> {
     let transaction = conn.transaction()?;
     {
-        let mut stmt = transaction.prepare(
-            "REPLACE INTO true_range (symbol, percentile_range, ema_range, timestamp)
-             VALUES (?1, ?2, ?3, ?4)",
-        )?;
+        let mut stmt = transaction.prepare(&format!(
+            "REPLACE INTO {table} (symbol, percentile_range, ema_range, timestamp)
+             VALUES (?1, ?2, ?3, ?4)"
+        ))?;
         for true_range in true_ranges {
             stmt.execute(params![
                 true_range.symbol,
@@ -41,17 +58,108 @@ pub fn save_true_ranges(conn: &mut Connection, true_ranges: &[model::TrueRange])
     transaction.commit()
 }
 
-pub fn get_true_range(conn: &Connection, symbol: &str) -> Result<model::TrueRange> {
-    conn.query_row(
-        "SELECT symbol,percentile_range,ema_range,timestamp FROM true_range where symbol = ?1",
-        [symbol],
-        |row| {
-            Ok(model::TrueRange {
-                symbol: row.get(0)?,
-                percentile_range: row.get(1)?,
-                ema_range: row.get(2)?,
-                timestamp: row.get(3)?,
-            })
-        },
-    )
+/// Retrieves the most recent ratio true range of each of the symbols in a single query.
+/// Symbols without a true range are absent from the map.
+pub fn get_true_ranges_bulk(
+    conn: &Connection,
+    symbols: &[&str],
+) -> Result<HashMap<String, model::TrueRange>> {
+    if symbols.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let placeholders = vec!["?"; symbols.len()].join(",");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT t.symbol,t.percentile_range,t.ema_range,t.timestamp FROM true_range t
+         WHERE t.symbol IN ({})
+         AND t.timestamp = (SELECT MAX(timestamp) FROM true_range WHERE symbol = t.symbol)",
+        placeholders
+    ))?;
+    let rows = stmt.query_map(params_from_iter(symbols), |row| {
+        Ok(model::TrueRange {
+            symbol: row.get(0)?,
+            percentile_range: row.get(1)?,
+            ema_range: row.get(2)?,
+            timestamp: row.get(3)?,
+        })
+    })?;
+    rows.map(|row| row.map(|true_range| (true_range.symbol.clone(), true_range)))
+        .collect()
+}
+
+/// Retrieves the last `window` true ranges of a symbol from a true range table, sorted from
+/// oldest to newest.
+pub fn get_true_range_rolling(
+    conn: &Connection,
+    table: &str,
+    symbol: &str,
+    window: u32,
+) -> Result<Vec<model::TrueRange>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT symbol,percentile_range,ema_range,timestamp FROM {table}
+         WHERE symbol = ?1 ORDER BY timestamp DESC LIMIT ?2"
+    ))?;
+    let rows = stmt.query_map(params![symbol, window], |row| {
+        Ok(model::TrueRange {
+            symbol: row.get(0)?,
+            percentile_range: row.get(1)?,
+            ema_range: row.get(2)?,
+            timestamp: row.get(3)?,
+        })
+    })?;
+    let mut true_ranges = rows.collect::<Result<Vec<_>>>()?;
+    true_ranges.reverse();
+    Ok(true_ranges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::sqlite;
+
+    fn true_range(symbol: &str, timestamp: u32) -> model::TrueRange {
+        model::TrueRange {
+            symbol: symbol.to_string(),
+            percentile_range: 0.05,
+            ema_range: 0.03,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn saved_true_ranges_are_read_back() {
+        let mut conn = sqlite::test_conn();
+        create_table_named(&conn, RATIO_TABLE).unwrap();
+        let true_ranges = [true_range("AAPL", 100), true_range("AAPL", 200)];
+        save_true_ranges_to(&mut conn, RATIO_TABLE, &true_ranges).unwrap();
+
+        let rolling = get_true_range_rolling(&conn, RATIO_TABLE, "AAPL", 10).unwrap();
+        let timestamps: Vec<u32> = rolling.iter().map(|t| t.timestamp).collect();
+        assert_eq!(timestamps, vec![100, 200]);
+
+        let bulk = get_true_ranges_bulk(&conn, &["AAPL", "MSFT"]).unwrap();
+        assert_eq!(bulk.len(), 1);
+        assert_eq!(bulk["AAPL"].timestamp, 200);
+        assert_eq!(bulk["AAPL"].percentile_range, 0.05);
+        assert_eq!(bulk["AAPL"].ema_range, 0.03);
+    }
+
+    #[test]
+    fn bulk_retrieval_returns_the_latest_true_range_of_every_symbol() {
+        let mut conn = sqlite::test_conn();
+        create_table_named(&conn, RATIO_TABLE).unwrap();
+        let symbols: Vec<String> = (0..10).map(|i| format!("SYM{}", i)).collect();
+        let true_ranges: Vec<model::TrueRange> = symbols
+            .iter()
+            .flat_map(|symbol| [true_range(symbol, 100), true_range(symbol, 200)])
+            .collect();
+        save_true_ranges_to(&mut conn, RATIO_TABLE, &true_ranges).unwrap();
+
+        let symbol_refs: Vec<&str> = symbols.iter().map(String::as_str).collect();
+        let bulk = get_true_ranges_bulk(&conn, &symbol_refs).unwrap();
+        assert_eq!(bulk.len(), 10);
+        for symbol in &symbols {
+            assert_eq!(bulk[symbol].timestamp, 200);
+        }
+        assert!(get_true_ranges_bulk(&conn, &[]).unwrap().is_empty());
+    }
 }
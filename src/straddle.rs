@@ -0,0 +1,81 @@
+use crate::{
+    model::{self, WithSymbol},
+    store::{implied_move, option_chain},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Estimates the implied move of every symbol from its latest option chain snapshot and saves
+/// it to the database. Only snapshots holding both calls and puts yield an estimate.
+pub fn calculate_and_save(
+    symbols_file_path: &str,        // Path to the file containing symbols.
+    conn: &mut Connection,          // Database connection.
+    warn_high_iv_move: Option<f64>, // Warn when the implied move is above this fraction.
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    implied_move::create_table(conn)?;
+
+    let mut implied_moves = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let chains = match option_chain::retrieve_option_chain(conn, &symbol, None) {
+            Ok(chains) => chains,
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                log::warn!(symbol = symbol.as_str(); "No option chain, skipping implied move");
+                continue;
+            }
+            Err(err) => return Err(err).with_symbol(&symbol),
+        };
+        let Some(first) = chains.first() else {
+            continue;
+        };
+        let updated = first.updated.clone();
+        let Some(implied_move_pct) = estimate_implied_move_pct(&chains, first.underlying_price)
+        else {
+            log::warn!(
+                symbol = symbol.as_str();
+                "Option chain lacks calls or puts, skipping implied move"
+            );
+            continue;
+        };
+        if let Some(threshold) = warn_high_iv_move {
+            if implied_move_pct > threshold {
+                log::warn!(
+                    symbol = symbol.as_str(),
+                    implied_move_pct = implied_move_pct,
+                    threshold = threshold;
+                    "Implied move is above the threshold"
+                );
+            }
+        }
+        implied_moves.push((symbol, implied_move_pct, updated));
+    }
+
+    implied_move::save_implied_moves(conn, &implied_moves)?;
+    Ok(())
+}
+
+/// The expected move to expiration as a fraction of the underlying price: the mid prices of
+/// the call and put nearest to the money summed and divided by `underlying_price`. Returns
+/// `None` unless both sides are present.
+pub fn estimate_implied_move_pct(
+    chains: &[model::OptionStrikeCandle],
+    underlying_price: f64,
+) -> Option<f64> {
+    if underlying_price <= 0.0 {
+        return None;
+    }
+    let nearest = |side: model::OptionChainSide| {
+        chains
+            .iter()
+            .filter(|chain| chain.side == side)
+            .min_by(|a, b| {
+                (a.strike - underlying_price)
+                    .abs()
+                    .total_cmp(&(b.strike - underlying_price).abs())
+            })
+    };
+    let call = nearest(model::OptionChainSide::Call)?;
+    let put = nearest(model::OptionChainSide::Put)?;
+    Some((call.mid + put.mid) / underlying_price)
+}
@@ -0,0 +1,159 @@
+use crate::{
+    config::Config,
+    model::{self, WithSymbol},
+    store::{candle, recovery_factor, streaks},
+    symbols,
+};
+use rusqlite::Connection;
+
+const SECONDS_PER_DAY: u32 = 86_400;
+
+/// Calculates the maximum drawdown duration of every symbol and saves it to the database.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+    warn_dd_duration_days: Option<u32>, // Warn when the maximum drawdown lasts longer than this.
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    streaks::create_table(conn)?;
+
+    let mut streak_vec = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        if candle::count_candles(conn, &symbol).with_symbol(&symbol)? == 0 {
+            log::warn!(symbol = symbol.as_str(); "No candles, skipping drawdown duration");
+            continue;
+        }
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let last_candle = candles.last().unwrap();
+
+        let max_dd_duration_days = max_drawdown_duration(&candles);
+        if let Some(threshold) = warn_dd_duration_days {
+            if max_dd_duration_days > threshold {
+                log::warn!(
+                    symbol = symbol.as_str(),
+                    max_dd_duration_days = max_dd_duration_days,
+                    threshold = threshold;
+                    "Maximum drawdown lasted longer than the threshold"
+                );
+            }
+        }
+
+        streak_vec.push((symbol.clone(), max_dd_duration_days, last_candle.timestamp));
+    }
+
+    streaks::save_streaks(conn, &streak_vec)?;
+    Ok(())
+}
+
+/// Calculates the recovery factor of every symbol and saves it to the database.
+pub fn calculate_and_save_recovery_factor(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    config: &Config,
+) -> model::Result<()> {
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    recovery_factor::create_table(conn)?;
+
+    let mut recovery_factor_vec = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let count = candle::count_candles(conn, &symbol).with_symbol(&symbol)?;
+        if count < 2 {
+            log::warn!(
+                symbol = symbol.as_str(), count = count;
+                "Not enough candles, skipping recovery factor"
+            );
+            continue;
+        }
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        let factor = match calculate_recovery_factor(&candles) {
+            Ok(factor) => factor,
+            Err(err) => {
+                log::warn!(symbol = symbol.as_str(), err:% = err; "Skipping recovery factor");
+                continue;
+            }
+        };
+        if factor < 1.0 {
+            log::warn!(
+                symbol = symbol.as_str(), recovery_factor = factor;
+                "Recovery factor below 1: total gain does not cover the worst drawdown"
+            );
+        }
+
+        recovery_factor_vec.push((symbol, factor, candles.last().unwrap().timestamp));
+    }
+
+    recovery_factor::save_recovery_factors(conn, &recovery_factor_vec)?;
+    Ok(())
+}
+
+/// Returns the maximum drawdown of the closing prices as a positive fraction of the peak.
+/// Candles must be sorted from oldest to newest.
+pub fn max_drawdown(candles: &[model::Candle]) -> f64 {
+    let mut peak = f64::MIN;
+    let mut max_drawdown = 0.0;
+    for candle in candles {
+        peak = peak.max(candle.close);
+        max_drawdown = f64::max(max_drawdown, 1.0 - candle.close / peak);
+    }
+    max_drawdown
+}
+
+/// Divides the total return over the candles by the absolute maximum drawdown.
+pub fn calculate_recovery_factor(candles: &[model::Candle]) -> model::Result<f64> {
+    if candles.len() < 2 {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            "Not enough candles for recovery factor calculation".to_string(),
+        ));
+    }
+    let max_drawdown = max_drawdown(candles);
+    if max_drawdown == 0.0 {
+        return Err(model::QuotesError::SharpeCalculationError(
+            "Max drawdown is zero, recovery factor is undefined".to_string(),
+        ));
+    }
+    let total_return = candles.last().unwrap().close / candles.first().unwrap().close - 1.0;
+    Ok(total_return / max_drawdown)
+}
+
+/// Returns the duration in calendar days of each drawdown episode, from the peak close to the
+/// first close that recovers it. A drawdown still open at the last candle is measured up to it.
+/// Candles must be sorted from oldest to newest.
+pub fn calculate_drawdown_durations(candles: &[model::Candle]) -> Vec<u32> {
+    let mut durations = Vec::new();
+    let Some(first) = candles.first() else {
+        return durations;
+    };
+
+    let mut peak = first;
+    let mut in_drawdown = false;
+    for candle in &candles[1..] {
+        if candle.close >= peak.close {
+            if in_drawdown {
+                durations.push((candle.timestamp - peak.timestamp) / SECONDS_PER_DAY);
+                in_drawdown = false;
+            }
+            peak = candle;
+        } else {
+            in_drawdown = true;
+        }
+    }
+    if in_drawdown {
+        let last = candles.last().unwrap();
+        durations.push((last.timestamp - peak.timestamp) / SECONDS_PER_DAY);
+    }
+    durations
+}
+
+/// Returns the longest drawdown duration in calendar days, or 0 when there was no drawdown.
+pub fn max_drawdown_duration(candles: &[model::Candle]) -> u32 {
+    calculate_drawdown_durations(candles)
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+}
@@ -1,11 +1,20 @@
 use std::{
+    collections::BTreeSet,
     fs::OpenOptions,
-    io::{BufRead, BufReader},
+    io::{BufRead, BufReader, BufWriter, Write},
     path::Path,
 };
 
-use crate::model::{QuotesError, Result};
+use rusqlite::Connection;
+use tempfile::NamedTempFile;
 
+use crate::{
+    constants,
+    model::{QuotesError, Result, WithSymbol},
+    store::{candle, max_drop, rolling_sharpe},
+};
+
+/// Reads one symbol per line. Blank lines and `#` comments, whole-line or trailing, are skipped.
 pub fn read_symbols_from_file(symbols_file_path: &str) -> Result<Vec<String>> {
     let path = Path::new(symbols_file_path);
     if !path.exists() {
@@ -18,9 +27,199 @@ pub fn read_symbols_from_file(symbols_file_path: &str) -> Result<Vec<String>> {
         .map(|line| line.map_err(|_e| QuotesError::CouldNotReadLine))
         .collect();
 
-    let symbols = lines?;
+    let symbols: Vec<String> = lines?
+        .iter()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|symbol| !symbol.is_empty())
+        .map(String::from)
+        .collect();
     if symbols.is_empty() {
         return Err(QuotesError::EmptySymbolFile(symbols_file_path.to_string()));
     }
     Ok(symbols)
 }
+
+// Where a command reads its symbols from: a symbols file or a comma-separated list.
+#[derive(clap::Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct SymbolsInput {
+    symbols_file_path: Option<String>,
+    // Comma-separated symbols, used instead of a symbols file.
+    #[arg(long, value_delimiter = ',')]
+    symbols: Option<Vec<String>>,
+}
+
+/// Path of the symbols file of a command. Inline symbols are written to a temporary file
+/// that is removed when this is dropped.
+pub struct SymbolsFile {
+    path: String,
+    _temp_file: Option<NamedTempFile>,
+}
+
+impl SymbolsFile {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl SymbolsInput {
+    /// Returns the symbols file, writing inline symbols to a temporary file first.
+    pub fn resolve(&self) -> Result<SymbolsFile> {
+        let Some(symbols) = &self.symbols else {
+            return Ok(SymbolsFile {
+                path: self.symbols_file_path.clone().unwrap_or_default(),
+                _temp_file: None,
+            });
+        };
+        let temp_file = NamedTempFile::new()?;
+        let path = temp_file.path().to_string_lossy().into_owned();
+        let symbols: Vec<&str> = symbols.iter().map(|s| s.trim()).collect();
+        write_symbols_to_file(&symbols, &path)?;
+        Ok(SymbolsFile {
+            path,
+            _temp_file: Some(temp_file),
+        })
+    }
+}
+
+/// Writes one symbol per line, replacing the file if it exists.
+pub fn write_symbols_to_file(symbols: &[&str], path: &str) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    let mut writer = BufWriter::new(file);
+    for symbol in symbols {
+        writeln!(writer, "{}", symbol)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the symbols that have at least `min_candles` candles and, when given, a latest
+/// rolling Sharpe ratio of at least `min_sharpe` to `output_file_path`.
+pub fn filter_symbols(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    output_file_path: &str,  // Path to write the qualifying symbols to.
+    min_candles: u32,
+    min_sharpe: Option<f64>,
+    conn: &Connection, // Database connection.
+) -> Result<()> {
+    let symbols = read_symbols_from_file(symbols_file_path)?;
+
+    candle::create_table(conn)?;
+    rolling_sharpe::create_table(conn)?;
+
+    let mut qualifying = Vec::with_capacity(symbols.len());
+    for symbol in &symbols {
+        let count = candle::count_candles(conn, symbol).with_symbol(symbol)?;
+        if count < min_candles {
+            log::info!(symbol = symbol.as_str(), count = count; "Filtered out: not enough candles");
+            continue;
+        }
+        if let Some(min_sharpe) = min_sharpe {
+            let sharpe = rolling_sharpe::get_latest_rolling_sharpe(
+                conn,
+                symbol,
+                constants::ROLLING_SHARPE_WINDOW,
+            )
+            .with_symbol(symbol)?;
+            if !sharpe.is_some_and(|sharpe| sharpe >= min_sharpe) {
+                log::info!(symbol = symbol.as_str(); "Filtered out: Sharpe ratio below minimum");
+                continue;
+            }
+        }
+        qualifying.push(symbol.as_str());
+    }
+
+    write_symbols_to_file(&qualifying, output_file_path)?;
+    log::info!(
+        "Wrote {} of {} symbols to {}",
+        qualifying.len(),
+        symbols.len(),
+        output_file_path
+    );
+    Ok(())
+}
+
+/// Prints the symbols of the file, or with `from_db` the symbols that have candles or max drops
+/// stored and which of the two they have. When both are given, symbols found in only one of
+/// the file and the candle table are logged.
+pub fn list_symbols(
+    symbols_file_path: Option<&str>, // Path to the file containing symbols.
+    from_db: bool,
+    conn: &Connection, // Database connection.
+) -> Result<()> {
+    let file_symbols = symbols_file_path
+        .map(read_symbols_from_file)
+        .transpose()?
+        .unwrap_or_default();
+    if !from_db {
+        for symbol in &file_symbols {
+            println!("{}", symbol);
+        }
+        return Ok(());
+    }
+
+    candle::create_table(conn)?;
+    max_drop::create_table_named(conn, "max_drop")?;
+    let candle_symbols: BTreeSet<String> = candle::get_all_symbols(conn)?.into_iter().collect();
+    let drop_symbols: BTreeSet<String> = max_drop::get_all_symbols(conn)?.into_iter().collect();
+
+    println!("{:<8} {:<8} MAX_DROP", "SYMBOL", "CANDLES");
+    for symbol in candle_symbols.union(&drop_symbols) {
+        let yes_no = |present: bool| if present { "yes" } else { "no" };
+        println!(
+            "{:<8} {:<8} {}",
+            symbol,
+            yes_no(candle_symbols.contains(symbol)),
+            yes_no(drop_symbols.contains(symbol))
+        );
+    }
+
+    if symbols_file_path.is_some() {
+        for symbol in &file_symbols {
+            if !candle_symbols.contains(symbol) {
+                log::warn!(symbol = symbol.as_str(); "In the symbols file but has no candles");
+            }
+        }
+        for symbol in &candle_symbols {
+            if !file_symbols.contains(symbol) {
+                log::info!(symbol = symbol.as_str(); "Has candles but is not in the symbols file");
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(contents: &str) -> Result<Vec<String>> {
+        let file = NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), contents).unwrap();
+        read_symbols_from_file(&file.path().to_string_lossy())
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_skipped() {
+        let symbols = read("# tech stocks\nAAPL\n \n# energy\nXOM\n").unwrap();
+        assert_eq!(symbols, vec!["AAPL", "XOM"]);
+    }
+
+    #[test]
+    fn inline_comments_are_stripped() {
+        let symbols = read("AAPL # Large cap tech\n  MSFT  \n").unwrap();
+        assert_eq!(symbols, vec!["AAPL", "MSFT"]);
+    }
+
+    #[test]
+    fn a_file_of_only_comments_is_empty() {
+        assert!(matches!(
+            read("# nothing here\n\n"),
+            Err(QuotesError::EmptySymbolFile(_))
+        ));
+    }
+}
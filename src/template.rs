@@ -0,0 +1,56 @@
+/// Values available to a Telegram caption template.
+#[derive(Debug)]
+pub struct MessageData {
+    pub date: String,        // {date}: when the chains were published.
+    pub symbol_count: usize, // {symbol_count}: number of underlyings with chains.
+    pub top_ror: f64,        // {top_ror}: highest rate of return across all chains.
+    pub avg_dte: f64,        // {avg_dte}: average days to expiration.
+    pub period: String,      // {period}: earliest to latest expiration.
+}
+
+/// Caption used when `TELEGRAM_MESSAGE_TEMPLATE` is not set.
+pub const DEFAULT_TEMPLATE: &str =
+    "Option chains {date}: {symbol_count} symbols, expiring {period} (avg DTE {avg_dte}), top RoR {top_ror}";
+
+/// Replaces each `{placeholder}` in the template with its value. Unknown placeholders are
+/// left as they are.
+pub fn render_message(template: &str, data: &MessageData) -> String {
+    template
+        .replace("{date}", &data.date)
+        .replace("{symbol_count}", &data.symbol_count.to_string())
+        .replace("{top_ror}", &format!("{:.3}", data.top_ror))
+        .replace("{avg_dte}", &format!("{:.1}", data.avg_dte))
+        .replace("{period}", &data.period)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data() -> MessageData {
+        MessageData {
+            date: "2026-10-18 09:30".to_string(),
+            symbol_count: 3,
+            top_ror: 0.0123,
+            avg_dte: 12.34,
+            period: "2026-10-23 to 2026-10-30".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_placeholder_is_replaced() {
+        assert_eq!(
+            render_message(DEFAULT_TEMPLATE, &data()),
+            "Option chains 2026-10-18 09:30: 3 symbols, expiring 2026-10-23 to 2026-10-30 \
+             (avg DTE 12.3), top RoR 0.012"
+        );
+    }
+
+    #[test]
+    fn unknown_placeholders_are_left_as_they_are() {
+        assert_eq!(
+            render_message("{symbol_count} {unknown} {symbol_count}", &data()),
+            "3 {unknown} 3"
+        );
+    }
+}
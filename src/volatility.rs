@@ -0,0 +1,39 @@
+use crate::{constants, model, statistics};
+
+/// Calculates the annualised historical volatility from the log returns of the closing prices.
+/// Candles must be sorted from oldest to newest.
+pub fn historical_volatility(candles: &[model::Candle]) -> model::Result<f64> {
+    if candles.len() < 3 {
+        return Err(model::QuotesError::not_enough_candles(
+            model::symbol_of(candles),
+            "Not enough candles for historical volatility calculation".to_string(),
+        ));
+    }
+
+    let returns: Vec<f64> = candles
+        .windows(2)
+        .map(|w| (w[1].close / w[0].close).ln())
+        .collect();
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+
+    Ok(variance.sqrt() * constants::TRADING_DAYS_PER_YEAR.sqrt())
+}
+
+/// Historical value at risk of the returns at `confidence`, as a positive loss fraction: the
+/// loss that the returns exceed only `1 - confidence` of the time.
+pub fn value_at_risk(returns: &[f64], confidence: f64) -> model::Result<f64> {
+    Ok(-statistics::percentile(returns, 1.0 - confidence)?)
+}
+
+/// Historical conditional value at risk of the returns at `confidence`, as a positive loss
+/// fraction: the average loss of the returns at or beyond the value at risk.
+pub fn conditional_value_at_risk(returns: &[f64], confidence: f64) -> model::Result<f64> {
+    let var = value_at_risk(returns, confidence)?;
+    let tail: Vec<f64> = returns.iter().copied().filter(|r| -r >= var).collect();
+    match tail.is_empty() {
+        true => Ok(var),
+        false => Ok(-tail.iter().sum::<f64>() / tail.len() as f64),
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{
+    config::Config,
+    model::{self, WithSymbol},
+    store::{candle, vwap},
+    symbols,
+};
+use rusqlite::Connection;
+
+/// Calculates the rolling VWAP of every symbol and saves the series to the database.
+/// Fails with `InvalidConfig` for a window of 0.
+pub fn calculate_and_save(
+    symbols_file_path: &str, // Path to the file containing symbols.
+    conn: &mut Connection,   // Database connection.
+    window: usize,           // Number of candles in each window.
+    config: &Config,
+) -> model::Result<()> {
+    if window == 0 {
+        return Err(model::QuotesError::InvalidConfig(
+            "VWAP window must be at least 1".to_string(),
+        ));
+    }
+    let symbols = symbols::read_symbols_from_file(symbols_file_path)?;
+
+    vwap::create_table(conn)?;
+
+    for symbol in symbols {
+        let candles = candle::get_candles_sorted_asc(conn, &symbol, config.candle_count)
+            .with_symbol(&symbol)?;
+        if candles.len() < window {
+            log::warn!(
+                symbol = symbol.as_str(), count = candles.len(), window = window;
+                "Not enough candles, skipping VWAP"
+            );
+            continue;
+        }
+
+        // Each value is keyed by the timestamp of the last candle in its window.
+        let vwaps: Vec<(u32, f64)> = candles[window - 1..]
+            .iter()
+            .map(|c| c.timestamp)
+            .zip(calculate_rolling_vwap(&candles, window))
+            .collect();
+        vwap::save_vwaps(conn, &symbol, window, &vwaps).with_symbol(&symbol)?;
+        log::info!(
+            symbol = symbol.as_str(), window = window, count = vwaps.len();
+            "Saved VWAP values"
+        );
+    }
+    Ok(())
+}
+
+/// Volume-weighted average of the typical prices of the candles.
+/// Returns 0.0 when there are no candles or no volume.
+pub fn calculate_vwap(candles: &[model::Candle]) -> f64 {
+    let (weighted, volume) = candles.iter().fold((0.0, 0.0), |(weighted, volume), c| {
+        (
+            weighted + c.typical_price() * c.volume as f64,
+            volume + c.volume as f64,
+        )
+    });
+    if volume == 0.0 {
+        return 0.0;
+    }
+    weighted / volume
+}
+
+/// VWAP of each window-sized slice of the candles, oldest first.
+pub fn calculate_rolling_vwap(candles: &[model::Candle], window: usize) -> Vec<f64> {
+    if window == 0 {
+        return Vec::new();
+    }
+    candles.windows(window).map(calculate_vwap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candle(high: f64, low: f64, close: f64, volume: u32) -> model::Candle {
+        model::Candle {
+            symbol: "TEST".to_string(),
+            open: close,
+            high,
+            low,
+            close,
+            volume,
+            timestamp: 0,
+        }
+    }
+
+    #[test]
+    fn vwap_of_no_candles_is_zero() {
+        assert_eq!(calculate_vwap(&[]), 0.0);
+    }
+
+    #[test]
+    fn vwap_of_one_candle_is_its_typical_price() {
+        assert_eq!(calculate_vwap(&[candle(12.0, 9.0, 9.0, 100)]), 10.0);
+    }
+
+    #[test]
+    fn vwap_weights_typical_prices_by_volume() {
+        // Typical prices 10 and 20 with volumes 300 and 100.
+        let candles = [candle(12.0, 9.0, 9.0, 300), candle(21.0, 19.0, 20.0, 100)];
+        assert_eq!(calculate_vwap(&candles), 12.5);
+    }
+
+    #[test]
+    fn rolling_vwap_has_one_value_per_window() {
+        let candles = [
+            candle(12.0, 9.0, 9.0, 100),
+            candle(21.0, 19.0, 20.0, 100),
+            candle(30.0, 30.0, 30.0, 100),
+        ];
+        assert_eq!(calculate_rolling_vwap(&candles, 2), vec![15.0, 25.0]);
+        assert!(calculate_rolling_vwap(&candles, 0).is_empty());
+    }
+}
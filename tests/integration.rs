@@ -0,0 +1,366 @@
+// End-to-end runs of the calculation pipeline against an in-memory database.
+use std::io::Write;
+
+use market_int::{
+    atr,
+    config::Config,
+    constants, maxdrop, model,
+    option::{self, AlertConfig, OptionChainFilterConfig},
+    quotes, sharpe,
+    store::{candle, sqlite},
+};
+use mockito::Matcher;
+use rusqlite::Connection;
+use tempfile::NamedTempFile;
+
+const SYMBOLS: [&str; 3] = ["AAPL", "MSFT", "XOM"];
+const CANDLE_COUNT: usize = 260;
+const FIRST_TIMESTAMP: u32 = 1_700_000_000;
+const DAY_SECS: u32 = 86_400;
+
+// Held by every test that reads or writes environment variables. Tests run in parallel, and
+// `set_var` in one test races with `Config::from_env` or the API token lookup in another.
+static ENV_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+// Writes the symbols to a temporary symbols file.
+fn symbols_file(symbols: &[&str]) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "{}", symbols.join("\n")).unwrap();
+    file
+}
+
+// Daily candles of a symbol that drift upwards while oscillating, so that every statistic has
+// some spread. `seed` shifts the oscillation between symbols.
+fn synthetic_candles(symbol: &str, seed: usize) -> Vec<model::Candle> {
+    (0..CANDLE_COUNT)
+        .map(|i| {
+            let close = 100.0 + 10.0 * ((i + seed * 7) as f64 / 9.0).sin() + i as f64 * 0.05;
+            let open = close - 0.5 * ((i + seed) as f64 / 3.0).cos();
+            model::Candle {
+                symbol: symbol.to_string(),
+                open,
+                high: open.max(close) + 1.0,
+                low: open.min(close) - 1.0,
+                close,
+                volume: 1_000_000,
+                timestamp: FIRST_TIMESTAMP + i as u32 * DAY_SECS,
+            }
+        })
+        .collect()
+}
+
+fn count(conn: &Connection, sql: &str) -> u32 {
+    conn.query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[tokio::test]
+async fn atr_sharpe_and_max_drop_are_saved_for_every_symbol() {
+    let _env = ENV_LOCK.lock().await;
+    let mut conn = sqlite::init_in_memory_connection().unwrap();
+    let config = Config::from_env();
+    let symbols_file = symbols_file(&SYMBOLS);
+    let symbols_path = symbols_file.path().to_str().unwrap();
+
+    candle::create_table(&conn).unwrap();
+    for (seed, symbol) in SYMBOLS.iter().enumerate() {
+        candle::save_candles(&mut conn, &synthetic_candles(symbol, seed))
+            .await
+            .unwrap();
+    }
+
+    atr::calculate_and_save(
+        symbols_path,
+        &mut conn,
+        atr::AtrMode::Ratio,
+        &config,
+        false,
+        false,
+    )
+    .unwrap();
+    let sharpe_config = model::SharpeConfig::builder()
+        .min_candles(constants::ROLLING_SHARPE_WINDOW)
+        .build();
+    sharpe::calculate_and_save_rolling(
+        symbols_path,
+        &mut conn,
+        constants::ROLLING_SHARPE_WINDOW,
+        &sharpe_config,
+    )
+    .unwrap();
+    maxdrop::calculate_and_save(
+        symbols_path,
+        &mut conn,
+        constants::MAX_DROP_PERIOD,
+        true,
+        &config,
+    )
+    .unwrap();
+
+    assert_eq!(
+        count(&conn, "SELECT COUNT(DISTINCT symbol) FROM true_range"),
+        3
+    );
+    assert_eq!(
+        count(&conn, "SELECT COUNT(DISTINCT symbol) FROM rolling_sharpe"),
+        3
+    );
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM max_drop"), 3);
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM max_drop_rolling"), 3);
+    let (percentile_range, ema_range): (f64, f64) = conn
+        .query_row(
+            "SELECT percentile_range, ema_range FROM true_range WHERE symbol = 'AAPL'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    // Ratio true ranges are fractions of the price.
+    assert!(percentile_range > 0.0 && percentile_range < 1.0);
+    assert!(ema_range > 0.0 && ema_range < 1.0);
+}
+
+// Candle response of the market data API for the synthetic candles.
+fn candles_response(candles: &[model::Candle]) -> String {
+    serde_json::json!({
+        "s": "ok",
+        "o": candles.iter().map(|c| c.open).collect::<Vec<_>>(),
+        "h": candles.iter().map(|c| c.high).collect::<Vec<_>>(),
+        "l": candles.iter().map(|c| c.low).collect::<Vec<_>>(),
+        "c": candles.iter().map(|c| c.close).collect::<Vec<_>>(),
+        "v": candles.iter().map(|c| c.volume).collect::<Vec<_>>(),
+        "t": candles.iter().map(|c| c.timestamp).collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+// Option chain response of the market data API holding two puts of a symbol.
+fn option_chain_response(symbol: &str) -> String {
+    let now = chrono::Local::now().timestamp();
+    let expiration = now + 14 * DAY_SECS as i64;
+    serde_json::json!({
+        "s": "ok",
+        "optionSymbol": [format!("{symbol}P90"), format!("{symbol}P95")],
+        "underlying": [symbol, symbol],
+        "expiration": [expiration, expiration],
+        "side": ["put", "put"],
+        "strike": [90.0, 95.0],
+        "dte": [14, 14],
+        "updated": [now, now],
+        "bid": [0.5, 1.0],
+        "bidSize": [10, 10],
+        "mid": [0.55, 1.05],
+        "ask": [0.6, 1.1],
+        "askSize": [10, 10],
+        "last": [0.55, 1.05],
+        "openInterest": [500, 800],
+        "volume": [100, 200],
+        "underlyingPrice": [100.0, 100.0],
+    })
+    .to_string()
+}
+
+// Pulls the put chains of the symbols without publishing them.
+async fn pull_put_chains(
+    symbols_path: &str,
+    conn: &mut Connection,
+    config: &Config,
+) -> model::Result<()> {
+    pull_chains(symbols_path, &[model::OptionChainSide::Put], conn, config).await
+}
+
+// Pulls the chains of the sides of the symbols without publishing them.
+async fn pull_chains(
+    symbols_path: &str,
+    sides: &[model::OptionChainSide],
+    conn: &mut Connection,
+    config: &Config,
+) -> model::Result<()> {
+    let alerts = AlertConfig {
+        oi_concentration_threshold: constants::OI_CONCENTRATION_THRESHOLD,
+        iv_hv_alert: None,
+        hide_best_options: false,
+    };
+    let filter = OptionChainFilterConfig {
+        expiry_dte: None,
+        min_dte: constants::MIN_DTE,
+        max_dte: constants::MAX_DTE,
+        min_liquidity_score: 0.0,
+        earnings_calendar: None,
+    };
+    option::retrieve_option_chains_base_on_ranges(
+        symbols_path,
+        sides,
+        conn,
+        config,
+        &alerts,
+        &filter,
+        false,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn pulled_quotes_feed_the_atr_and_option_chain_steps() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    for (seed, symbol) in SYMBOLS.iter().enumerate() {
+        server
+            .mock("GET", format!("/v1/stocks/candles/daily/{symbol}").as_str())
+            .match_query(Matcher::Any)
+            .with_header("content-type", "application/json")
+            .with_body(candles_response(&synthetic_candles(symbol, seed)))
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("/v1/options/chain/{symbol}/").as_str())
+            .match_query(Matcher::UrlEncoded("side".into(), "put".into()))
+            .with_header("content-type", "application/json")
+            .with_body(option_chain_response(symbol))
+            .create_async()
+            .await;
+    }
+    std::env::set_var("MARKETDATA_BASE_URL", format!("{}/", server.url()));
+    std::env::set_var("marketdata_token", "test-token");
+
+    let mut conn = sqlite::init_in_memory_connection().unwrap();
+    let config = Config::from_env();
+    let symbols_file = symbols_file(&SYMBOLS);
+    let symbols_path = symbols_file.path().to_str().unwrap();
+
+    quotes::pull_and_save(
+        symbols_path,
+        &mut conn,
+        &config,
+        true,
+        model::Period::Daily,
+        None,
+    )
+    .await
+    .unwrap();
+    for symbol in SYMBOLS {
+        assert_eq!(
+            candle::count_candles(&conn, symbol).unwrap(),
+            CANDLE_COUNT as u32
+        );
+    }
+
+    atr::calculate_and_save(
+        symbols_path,
+        &mut conn,
+        atr::AtrMode::Ratio,
+        &config,
+        false,
+        false,
+    )
+    .unwrap();
+
+    pull_put_chains(symbols_path, &mut conn, &config)
+        .await
+        .unwrap();
+
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM option_strike"), 6);
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM runs WHERE completed_at IS NOT NULL"
+        ),
+        1
+    );
+    let strike_from: f64 = conn
+        .query_row(
+            "SELECT strike_from FROM option_strike WHERE underlying = 'AAPL' LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    // The strike range is built from the latest close and the ratio true range.
+    assert!(strike_from > 0.0 && strike_from < 115.0);
+}
+
+#[tokio::test]
+async fn symbols_without_a_true_range_are_skipped_and_the_run_completes() {
+    let _env = ENV_LOCK.lock().await;
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", "/v1/options/chain/AAPL/")
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(option_chain_response("AAPL"))
+        .create_async()
+        .await;
+    std::env::set_var("MARKETDATA_BASE_URL", format!("{}/", server.url()));
+    std::env::set_var("marketdata_token", "test-token");
+
+    let mut conn = sqlite::init_in_memory_connection().unwrap();
+    let config = Config::from_env();
+    candle::create_table(&conn).unwrap();
+    candle::save_candles(&mut conn, &synthetic_candles("AAPL", 0))
+        .await
+        .unwrap();
+    let aapl_file = symbols_file(&["AAPL"]);
+    atr::calculate_and_save(
+        aapl_file.path().to_str().unwrap(),
+        &mut conn,
+        atr::AtrMode::Ratio,
+        &config,
+        false,
+        false,
+    )
+    .unwrap();
+
+    // TSLA has neither candles nor a true range.
+    let both_file = symbols_file(&["AAPL", "TSLA"]);
+    pull_put_chains(both_file.path().to_str().unwrap(), &mut conn, &config)
+        .await
+        .unwrap();
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM option_strike"), 2);
+
+    let tsla_file = symbols_file(&["TSLA"]);
+    let result = pull_put_chains(tsla_file.path().to_str().unwrap(), &mut conn, &config).await;
+    assert!(matches!(result, Err(model::QuotesError::PartialFailure(_))));
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT COUNT(*) FROM runs WHERE completed_at IS NOT NULL"
+        ),
+        2
+    );
+}
+
+#[tokio::test]
+async fn a_symbol_whose_sides_all_fail_is_counted_once() {
+    let _env = ENV_LOCK.lock().await;
+    // Without mocks, every option chain request fails.
+    let server = mockito::Server::new_async().await;
+    std::env::set_var("MARKETDATA_BASE_URL", format!("{}/", server.url()));
+    std::env::set_var("marketdata_token", "test-token");
+
+    let mut conn = sqlite::init_in_memory_connection().unwrap();
+    let config = Config::from_env();
+    candle::create_table(&conn).unwrap();
+    candle::save_candles(&mut conn, &synthetic_candles("AAPL", 0))
+        .await
+        .unwrap();
+    let aapl_file = symbols_file(&["AAPL"]);
+    let aapl_path = aapl_file.path().to_str().unwrap();
+    atr::calculate_and_save(
+        aapl_path,
+        &mut conn,
+        atr::AtrMode::Ratio,
+        &config,
+        false,
+        false,
+    )
+    .unwrap();
+
+    let result = pull_chains(
+        aapl_path,
+        &model::OptionChainSide::iter_sides().collect::<Vec<_>>(),
+        &mut conn,
+        &config,
+    )
+    .await;
+    match result {
+        Err(model::QuotesError::PartialFailure(failures)) => assert_eq!(failures.len(), 1),
+        other => panic!("expected a partial failure, got {:?}", other),
+    }
+}